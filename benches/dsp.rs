@@ -0,0 +1,142 @@
+// Criterion benches for the DSP hot paths so CPU regressions show up before a release
+// Run with: cargo bench --bench dsp
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use nih_plug::prelude::*;
+use Subhoofer::{
+    b_bass_saturation, c_bass_saturation, chebyshev_tape, custom_sincos_saturation, leaf_saturation, tape_saturation,
+    AlgorithmType, SubSettings, Subhoofer as SubhooferPlugin, SweetenX, BLOCK_SIZE,
+};
+
+const SAMPLE_RATE: f32 = 48000.0;
+const FRAME_SIZES: [usize; 3] = [64, 512, 2048];
+
+// Default knob values so the functions do the same work they do in a session
+const HARMONICS: f32 = 0.000580;
+const HOOF_HARDNESS: f32 = 0.0093;
+
+// A bass line like signal with some upper content so every stage has work to do
+fn test_signal(num_samples: usize) -> Vec<f32> {
+    (0..num_samples)
+        .map(|idx| {
+            let t = idx as f32 / SAMPLE_RATE;
+            0.5 * (2.0 * std::f32::consts::PI * 55.0 * t).sin() + 0.1 * (2.0 * std::f32::consts::PI * 440.0 * t).sin()
+        })
+        .collect()
+}
+
+// Point the algorithm parameter at one variant through the param map like a host would
+fn set_algorithm(plugin: &SubhooferPlugin, index: usize) {
+    let normalized = index as f32 / (AlgorithmType::variants().len() - 1) as f32;
+    for (id, param_ptr, _) in plugin.params().param_map() {
+        if id == "Algorithm" {
+            unsafe { param_ptr.set_normalized_value(normalized) };
+        }
+    }
+}
+
+fn sweeten(c: &mut Criterion) {
+    let input = test_signal(BLOCK_SIZE);
+    let mut buffer: SweetenX::Buffer = [[0.0; 2]; 8];
+    let mut group = c.benchmark_group("SweetenX");
+    group.throughput(Throughput::Elements(input.len() as u64));
+    for harmonic in [2, 5, 9] {
+        group.bench_with_input(BenchmarkId::new("process", harmonic), &harmonic, |b, &harmonic| {
+            b.iter(|| {
+                for sample in input.iter() {
+                    black_box(SweetenX::process(black_box(*sample), black_box(*sample), 1.0, 8.941176, harmonic, &mut buffer));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn saturation(c: &mut Criterion) {
+    let input = test_signal(BLOCK_SIZE);
+    let mut group = c.benchmark_group("saturation");
+    group.throughput(Throughput::Elements(input.len() as u64));
+    group.bench_function("b_bass_saturation", |b| {
+        b.iter(|| input.iter().map(|sample| b_bass_saturation(black_box(*sample), HARMONICS)).sum::<f32>())
+    });
+    group.bench_function("c_bass_saturation", |b| {
+        b.iter(|| input.iter().map(|sample| c_bass_saturation(black_box(*sample), HARMONICS)).sum::<f32>())
+    });
+    group.bench_function("custom_sincos_saturation", |b| {
+        b.iter(|| {
+            input
+                .iter()
+                .map(|sample| custom_sincos_saturation(black_box(*sample), HARMONICS, HARMONICS, HARMONICS, HARMONICS))
+                .sum::<f32>()
+        })
+    });
+    group.bench_function("tape_saturation", |b| {
+        b.iter(|| input.iter().map(|sample| tape_saturation(black_box(*sample), HARMONICS)).sum::<f32>())
+    });
+    group.bench_function("chebyshev_tape", |b| {
+        b.iter(|| input.iter().map(|sample| chebyshev_tape(black_box(*sample), HOOF_HARDNESS)).sum::<f32>())
+    });
+    group.bench_function("leaf_saturation", |b| {
+        let threshold = util::db_to_gain(-30.0);
+        b.iter(|| input.iter().map(|sample| leaf_saturation(black_box(*sample), threshold, 0.5)).sum::<f32>())
+    });
+    group.finish();
+}
+
+fn sub_cascade(c: &mut Criterion) {
+    let input = test_signal(BLOCK_SIZE);
+    let mut plugin = SubhooferPlugin::default();
+    let settings = SubSettings {
+        hoof_hardness: HOOF_HARDNESS,
+        sub_gain: 0.5,
+        sub_drive: 0.1,
+        divider_gains: [1.0, 0.0, 0.0],
+        sub_shape: 2.0,
+        sub_phase: 0.0,
+        sub_invert: false,
+        auto_align: false,
+        min_freq: 10.0,
+        max_freq: 1000.0,
+        overall_scale: SAMPLE_RATE / 44100.0,
+    };
+    let mut group = c.benchmark_group("sub");
+    group.throughput(Throughput::Elements(input.len() as u64));
+    group.bench_function("process_sub", |b| {
+        b.iter(|| {
+            for sample in input.iter() {
+                black_box(plugin.process_sub(black_box(*sample * 2.0), black_box(*sample * 2.0), &settings));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn full_block(c: &mut Criterion) {
+    let input = test_signal(*FRAME_SIZES.iter().max().unwrap());
+    for (index, name) in AlgorithmType::variants().iter().enumerate() {
+        let mut group = c.benchmark_group(format!("process/{name}"));
+        for frames in FRAME_SIZES {
+            let mut plugin = SubhooferPlugin::default();
+            set_algorithm(&plugin, index);
+            let mut left = vec![0.0; frames];
+            let mut right = vec![0.0; frames];
+
+            group.throughput(Throughput::Elements(frames as u64));
+            group.bench_with_input(BenchmarkId::from_parameter(frames), &frames, |b, &frames| {
+                b.iter(|| {
+                    // Same input each time so the state doesn't drift
+                    left.copy_from_slice(&input[..frames]);
+                    right.copy_from_slice(&input[..frames]);
+                    for (left, right) in left.chunks_mut(BLOCK_SIZE).zip(right.chunks_mut(BLOCK_SIZE)) {
+                        plugin.process_block(&mut [left, right], SAMPLE_RATE);
+                    }
+                    black_box(&left);
+                })
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, sweeten, saturation, sub_cascade, full_block);
+criterion_main!(benches);
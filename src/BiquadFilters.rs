@@ -0,0 +1,141 @@
+// Biquad filters from the RBJ Audio EQ Cookbook
+// Ardura
+
+use std::f32::consts::PI;
+
+// Butterworth Q, cascade two of these for a 24dB/oct Linkwitz-Riley split
+pub const BUTTERWORTH_Q: f32 = 0.70710678;
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum FilterType {
+    LowPass,
+    HighPass,
+    AllPass,
+    LowShelf,
+    HighShelf,
+}
+
+#[derive(Clone, Copy)]
+pub struct Biquad {
+    filter_type: FilterType,
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+    // Last settings so we only recalculate coefficients on changes
+    sample_rate: f32,
+    freq: f32,
+    q: f32,
+    gain_db: f32,
+}
+
+#[allow(dead_code)]
+impl Biquad {
+    // Starts as a passthrough until set() is called
+    pub fn new(filter_type: FilterType) -> Self {
+        Self {
+            filter_type,
+            b0: 1.0,
+            b1: 0.0,
+            b2: 0.0,
+            a1: 0.0,
+            a2: 0.0,
+            z1: 0.0,
+            z2: 0.0,
+            sample_rate: 0.0,
+            freq: 0.0,
+            q: 0.0,
+            gain_db: 0.0,
+        }
+    }
+
+    // Update the filter, gain_db is only used by the shelves
+    pub fn set(&mut self, sample_rate: f32, freq: f32, q: f32, gain_db: f32) {
+        if sample_rate == self.sample_rate && freq == self.freq && q == self.q && gain_db == self.gain_db {
+            return;
+        }
+        self.sample_rate = sample_rate;
+        self.freq = freq;
+        self.q = q;
+        self.gain_db = gain_db;
+
+        // Keep the corner safely under nyquist
+        let freq = freq.clamp(1.0, sample_rate * 0.49);
+        let w0 = 2.0 * PI * freq / sample_rate;
+        let cos_w0 = w0.cos();
+        let alpha = w0.sin() / (2.0 * q);
+        let a = 10.0_f32.powf(gain_db / 40.0);
+
+        let (b0, b1, b2, a0, a1, a2) = match self.filter_type {
+            FilterType::LowPass => (
+                (1.0 - cos_w0) / 2.0,
+                1.0 - cos_w0,
+                (1.0 - cos_w0) / 2.0,
+                1.0 + alpha,
+                -2.0 * cos_w0,
+                1.0 - alpha,
+            ),
+            FilterType::HighPass => (
+                (1.0 + cos_w0) / 2.0,
+                -(1.0 + cos_w0),
+                (1.0 + cos_w0) / 2.0,
+                1.0 + alpha,
+                -2.0 * cos_w0,
+                1.0 - alpha,
+            ),
+            FilterType::AllPass => (
+                1.0 - alpha,
+                -2.0 * cos_w0,
+                1.0 + alpha,
+                1.0 + alpha,
+                -2.0 * cos_w0,
+                1.0 - alpha,
+            ),
+            FilterType::LowShelf => {
+                let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+                (
+                    a * ((a + 1.0) - (a - 1.0) * cos_w0 + sqrt_a_alpha),
+                    2.0 * a * ((a - 1.0) - (a + 1.0) * cos_w0),
+                    a * ((a + 1.0) - (a - 1.0) * cos_w0 - sqrt_a_alpha),
+                    (a + 1.0) + (a - 1.0) * cos_w0 + sqrt_a_alpha,
+                    -2.0 * ((a - 1.0) + (a + 1.0) * cos_w0),
+                    (a + 1.0) + (a - 1.0) * cos_w0 - sqrt_a_alpha,
+                )
+            }
+            FilterType::HighShelf => {
+                let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+                (
+                    a * ((a + 1.0) + (a - 1.0) * cos_w0 + sqrt_a_alpha),
+                    -2.0 * a * ((a - 1.0) + (a + 1.0) * cos_w0),
+                    a * ((a + 1.0) + (a - 1.0) * cos_w0 - sqrt_a_alpha),
+                    (a + 1.0) - (a - 1.0) * cos_w0 + sqrt_a_alpha,
+                    2.0 * ((a - 1.0) - (a + 1.0) * cos_w0),
+                    (a + 1.0) - (a - 1.0) * cos_w0 - sqrt_a_alpha,
+                )
+            }
+        };
+
+        self.b0 = b0 / a0;
+        self.b1 = b1 / a0;
+        self.b2 = b2 / a0;
+        self.a1 = a1 / a0;
+        self.a2 = a2 / a0;
+    }
+
+    // Transposed direct form II
+    pub fn process(&mut self, input: f32) -> f32 {
+        let output = self.b0 * input + self.z1;
+        self.z1 = self.b1 * input - self.a1 * output + self.z2;
+        self.z2 = self.b2 * input - self.a2 * output;
+        output
+    }
+
+    pub fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+}
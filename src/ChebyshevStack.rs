@@ -0,0 +1,41 @@
+// Chebyshev polynomial harmonic stack used by the drawbar algorithm
+// Ardura
+
+// Harmonic orders the drawbars cover
+pub const FIRST_ORDER: usize = 2;
+pub const LAST_ORDER: usize = 16;
+pub const NUM_ORDERS: usize = LAST_ORDER - FIRST_ORDER + 1;
+
+// T_n(cos(w)) = cos(n * w) so a full scale sine comes out as exactly the nth harmonic.
+// The gains are signed so a negative gain flips the phase of that harmonic.
+// Left and right run side by side through the same recurrence
+pub fn process(samples: [f32; 2], gains: &[f32; NUM_ORDERS]) -> [f32; 2] {
+    // The polynomials blow up outside of -1..1
+    let x: [f32; 2] = [samples[0].clamp(-1.0, 1.0), samples[1].clamp(-1.0, 1.0)];
+
+    // T0 and T1 to start the recurrence T(n+1) = 2x * T(n) - T(n-1)
+    let mut t_prev: [f32; 2] = [1.0; 2];
+    let mut t_curr: [f32; 2] = x;
+    let mut summed: [f32; 2] = [0.0; 2];
+
+    for (idx, gain) in gains.iter().enumerate() {
+        let t_next: [f32; 2] = [2.0 * x[0] * t_curr[0] - t_prev[0], 2.0 * x[1] * t_curr[1] - t_prev[1]];
+        t_prev = t_curr;
+        t_curr = t_next;
+
+        if *gain != 0.0 {
+            let offset: f32 = silent_offset(idx + FIRST_ORDER);
+            summed = [summed[0] + gain * (t_curr[0] - offset), summed[1] + gain * (t_curr[1] - offset)];
+        }
+    }
+    summed
+}
+
+// Even orders sit at +/-1 with no input which would be a big DC jump, so remove it here
+fn silent_offset(order: usize) -> f32 {
+    match order % 4 {
+        0 => 1.0,
+        2 => -1.0,
+        _ => 0.0,
+    }
+}
@@ -1,2 +1,5 @@
 pub(crate) mod db_meter;
+pub(crate) mod harmonic_drawbars;
+pub(crate) mod signal_plot;
+pub(crate) mod theme;
 pub(crate) mod ui_knob;
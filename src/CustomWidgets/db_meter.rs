@@ -1,6 +1,7 @@
 // db_meter.rs - Ardura 2023
 // A decibel meter akin to Vizia's nice one in nih-plug
 
+use super::theme::Theme;
 use nih_plug_egui::egui::{lerp, vec2, Align2, Color32, FontId, NumExt, Pos2, Rect, Response, Sense, Shape, Stroke, Ui, Widget, WidgetText};
 
 // TODO - let percentage work?
@@ -14,7 +15,9 @@ enum DBMeterText {
 pub struct DBMeter {
     level: f32,
     desired_width: Option<f32>,
+    desired_height: Option<f32>,
     text: String,
+    text_size: f32,
     animate: bool,
     border_color: Color32,
     bar_color: Color32,
@@ -28,7 +31,9 @@ impl DBMeter {
         Self {
             level: level.clamp(0.0, 1.0),
             desired_width: None,
+            desired_height: None,
             text: String::new(),
+            text_size: 10.0,
             animate: false,
             border_color: Color32::BLACK,
             bar_color: Color32::GREEN,
@@ -42,6 +47,18 @@ impl DBMeter {
         self
     }
 
+    /// The height of the bar. Will use the interact size if not set.
+    pub fn desired_height(mut self, desired_height: f32) -> Self {
+        self.desired_height = Some(desired_height);
+        self
+    }
+
+    /// Size of the meter text
+    pub fn text_size(mut self, text_size: f32) -> Self {
+        self.text_size = text_size;
+        self
+    }
+
     /// A custom text to display on the progress bar.
     pub fn text(mut self, text: String) -> Self {
         self.text = text;
@@ -62,6 +79,13 @@ impl DBMeter {
     pub fn set_background_color(&mut self, new_color: Color32) {
         self.background_color = new_color;
     }
+
+    /// Take all colors from a theme
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.background_color = theme.panel;
+        self.bar_color = theme.primary;
+        self.border_color = theme.outline;
+    }
 }
 
 impl Widget for DBMeter {
@@ -70,7 +94,9 @@ impl Widget for DBMeter {
         let DBMeter {
             level,
             desired_width,
+            desired_height,
             ref text,
+            text_size,
             animate, 
             border_color, 
             bar_color, 
@@ -80,7 +106,7 @@ impl Widget for DBMeter {
 
         let desired_width =
             desired_width.unwrap_or_else(|| ui.available_size_before_wrap().x.at_least(96.0));
-        let height = ui.spacing().interact_size.y;
+        let height = desired_height.unwrap_or(ui.spacing().interact_size.y);
         let (outer_rect, response) =
             ui.allocate_exact_size(vec2(desired_width, height), Sense::hover());
 
@@ -150,7 +176,7 @@ impl Widget for DBMeter {
                 .override_text_color
                 .unwrap_or(self.border_color);
             let temp: String = self.text;
-            ui.painter().text(text_pos, Align2::LEFT_CENTER, temp, FontId::monospace(10.0), text_color);
+            ui.painter().text(text_pos, Align2::LEFT_CENTER, temp, FontId::monospace(text_size), text_color);
         }
 
         response
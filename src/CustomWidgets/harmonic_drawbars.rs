@@ -0,0 +1,203 @@
+// harmonic_drawbars.rs - Ardura 2024
+// Bar graph editor for the Chebyshev harmonic gains, one bar per harmonic order
+
+use lazy_static::lazy_static;
+use super::theme::Theme;
+use nih_plug::prelude::{BoolParam, FloatParam, Param, ParamSetter};
+use nih_plug_egui::egui::{
+    self, Align2, Color32, FontId, Pos2, Rect, Response, Rounding, Sense, Stroke, Ui, Vec2, Widget,
+};
+
+lazy_static! {
+    // Which bar is currently being dragged so gestures can be ended properly
+    static ref ACTIVE_BAR_MEMORY_ID: egui::Id = egui::Id::new((file!(), 0));
+}
+
+// Height of the polarity toggles under the bars and the font sizes, at 100% zoom
+const POLARITY_HEIGHT: f32 = 14.0;
+const ORDER_FONT_SIZE: f32 = 9.0;
+const POLARITY_FONT_SIZE: f32 = 11.0;
+
+#[must_use = "You should put this widget in an ui with `ui.add(widget);`"]
+pub struct HarmonicDrawbars<'a> {
+    gains: Vec<&'a FloatParam>,
+    inverts: Vec<&'a BoolParam>,
+    param_setter: &'a ParamSetter<'a>,
+    first_order: usize,
+    size: Vec2,
+    bar_color: Color32,
+    inverted_color: Color32,
+    background_color: Color32,
+    text_color: Color32,
+    scale: f32,
+}
+
+#[allow(dead_code)]
+impl<'a> HarmonicDrawbars<'a> {
+    /// `gains` and `inverts` should be the same length, starting at harmonic `first_order`
+    pub fn for_params(
+        gains: Vec<&'a FloatParam>,
+        inverts: Vec<&'a BoolParam>,
+        param_setter: &'a ParamSetter<'a>,
+        first_order: usize,
+        size: Vec2,
+    ) -> Self {
+        Self {
+            gains,
+            inverts,
+            param_setter,
+            first_order,
+            size,
+            bar_color: Color32::GREEN,
+            inverted_color: Color32::RED,
+            background_color: Color32::GRAY,
+            text_color: Color32::BLACK,
+            scale: 1.0,
+        }
+    }
+
+    // Take all colors from a theme
+    pub fn set_theme(mut self, theme: &Theme) -> Self {
+        self.bar_color = theme.primary;
+        self.inverted_color = theme.secondary;
+        self.background_color = theme.panel;
+        self.text_color = theme.outline;
+        self
+    }
+
+    // Color of bars in normal polarity
+    pub fn set_bar_color(mut self, new_color: Color32) -> Self {
+        self.bar_color = new_color;
+        self
+    }
+
+    // Color of bars with flipped polarity
+    pub fn set_inverted_color(mut self, new_color: Color32) -> Self {
+        self.inverted_color = new_color;
+        self
+    }
+
+    // Background behind the bars
+    pub fn set_background_color(mut self, new_color: Color32) -> Self {
+        self.background_color = new_color;
+        self
+    }
+
+    // Color of order numbers and outlines
+    pub fn set_text_color(mut self, new_color: Color32) -> Self {
+        self.text_color = new_color;
+        self
+    }
+
+    // Editor zoom for the text and polarity strip, the same factor the knobs use
+    pub fn set_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    fn get_active_bar(ui: &Ui) -> Option<usize> {
+        ui.memory(|mem| mem.data.get_temp(*ACTIVE_BAR_MEMORY_ID))
+            .unwrap_or(None)
+    }
+
+    fn set_active_bar(ui: &Ui, bar: Option<usize>) {
+        ui.memory_mut(|mem| mem.data.insert_temp(*ACTIVE_BAR_MEMORY_ID, bar));
+    }
+
+    // Figure out which bar a pointer position lands on
+    fn bar_at(&self, bars_rect: Rect, pos: Pos2) -> usize {
+        let bar_width = bars_rect.width() / self.gains.len() as f32;
+        (((pos.x - bars_rect.left()) / bar_width).floor().max(0.0) as usize).min(self.gains.len() - 1)
+    }
+}
+
+impl<'a> Widget for HarmonicDrawbars<'a> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let mut response = ui.allocate_response(self.size, Sense::click_and_drag());
+        let rect = response.rect;
+        let polarity_height = POLARITY_HEIGHT * self.scale;
+        let bars_rect = Rect::from_min_max(rect.min, Pos2::new(rect.right(), rect.bottom() - polarity_height));
+        let num_bars = self.gains.len();
+        let bar_width = bars_rect.width() / num_bars as f32;
+
+        if let Some(pointer) = response.interact_pointer_pos() {
+            let bar = self.bar_at(bars_rect, pointer);
+            if pointer.y > bars_rect.bottom() {
+                // Polarity strip toggles on click only
+                if response.clicked() {
+                    let invert = self.inverts[bar];
+                    self.param_setter.begin_set_parameter(invert);
+                    self.param_setter.set_parameter(invert, !invert.value());
+                    self.param_setter.end_set_parameter(invert);
+                    response.mark_changed();
+                }
+            } else if response.double_clicked() {
+                let gain = self.gains[bar];
+                self.param_setter.begin_set_parameter(gain);
+                self.param_setter.set_parameter(gain, gain.default_plain_value());
+                self.param_setter.end_set_parameter(gain);
+                response.mark_changed();
+            } else {
+                // Dragging across the bars draws the profile like on an organ
+                let active = Self::get_active_bar(ui);
+                if active != Some(bar) {
+                    if let Some(previous) = active {
+                        self.param_setter.end_set_parameter(self.gains[previous]);
+                    }
+                    self.param_setter.begin_set_parameter(self.gains[bar]);
+                    Self::set_active_bar(ui, Some(bar));
+                }
+                let normalized = ((bars_rect.bottom() - pointer.y) / bars_rect.height()).clamp(0.0, 1.0);
+                self.param_setter.set_parameter_normalized(self.gains[bar], normalized);
+                response.mark_changed();
+            }
+        }
+        if response.drag_released() || (response.clicked() && !response.dragged()) {
+            if let Some(previous) = Self::get_active_bar(ui) {
+                self.param_setter.end_set_parameter(self.gains[previous]);
+                Self::set_active_bar(ui, None);
+            }
+        }
+
+        if ui.is_rect_visible(rect) {
+            let painter = ui.painter_at(rect);
+            painter.rect(rect, Rounding::from(4.0), self.background_color, Stroke::new(1.0, self.text_color));
+
+            for (idx, (gain, invert)) in self.gains.iter().zip(self.inverts.iter()).enumerate() {
+                let left = bars_rect.left() + bar_width * idx as f32;
+                let level = gain.modulated_normalized_value();
+                let color = if invert.value() { self.inverted_color } else { self.bar_color };
+                let bar = Rect::from_min_max(
+                    Pos2::new(left + 1.0, bars_rect.bottom() - bars_rect.height() * level),
+                    Pos2::new(left + bar_width - 1.0, bars_rect.bottom()),
+                );
+                painter.rect_filled(bar, Rounding::from(2.0), color);
+                painter.text(
+                    Pos2::new(left + bar_width / 2.0, bars_rect.top() + 6.0 * self.scale),
+                    Align2::CENTER_CENTER,
+                    (idx + self.first_order).to_string(),
+                    FontId::monospace(ORDER_FONT_SIZE * self.scale),
+                    self.text_color,
+                );
+                painter.text(
+                    Pos2::new(left + bar_width / 2.0, bars_rect.bottom() + polarity_height / 2.0),
+                    Align2::CENTER_CENTER,
+                    if invert.value() { "-" } else { "+" },
+                    FontId::monospace(POLARITY_FONT_SIZE * self.scale),
+                    color,
+                );
+            }
+            painter.line_segment(
+                [bars_rect.left_bottom(), bars_rect.right_bottom()],
+                Stroke::new(1.0, self.text_color),
+            );
+        }
+
+        response.on_hover_text_at_pointer(
+            "Harmonic levels when using \"Chebyshev Drawbars\" Algorithm
+Drag bars to set each harmonic's level
+Click +/- to flip a harmonic's phase
+Double-click a bar to reset",
+        )
+    }
+}
@@ -0,0 +1,180 @@
+// signal_plot.rs - Ardura 2024
+// Transfer curve and scope plots for the visualizer page, plus the buffer the audio thread fills
+
+use super::theme::Theme;
+use atomic_float::AtomicF32;
+use nih_plug_egui::egui::{pos2, Color32, Pos2, Response, Rounding, Sense, Shape, Stroke, Ui, Vec2, Widget};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Lock free ring buffer the audio thread writes into for the scope
+pub struct ScopeBuffer {
+    samples: Vec<AtomicF32>,
+    position: AtomicUsize,
+}
+
+impl ScopeBuffer {
+    pub fn new(size: usize) -> Self {
+        Self {
+            samples: (0..size).map(|_| AtomicF32::new(0.0)).collect(),
+            position: AtomicUsize::new(0),
+        }
+    }
+
+    /// Only the audio thread should push
+    pub fn push(&self, value: f32) {
+        let position = self.position.load(Ordering::Relaxed);
+        self.samples[position].store(value, Ordering::Relaxed);
+        self.position.store((position + 1) % self.samples.len(), Ordering::Relaxed);
+    }
+
+    /// Copy of the buffer from oldest to newest sample
+    pub fn snapshot(&self) -> Vec<f32> {
+        let position = self.position.load(Ordering::Relaxed);
+        (0..self.samples.len())
+            .map(|idx| self.samples[(position + idx) % self.samples.len()].load(Ordering::Relaxed))
+            .collect()
+    }
+}
+
+#[must_use = "You should put this widget in an ui with `ui.add(widget);`"]
+pub struct TransferCurve {
+    // Output values for inputs evenly spaced over -1..1
+    points: Vec<f32>,
+    size: Vec2,
+    curve_color: Color32,
+    reference_color: Color32,
+    background_color: Color32,
+    border_color: Color32,
+}
+
+#[allow(dead_code)]
+impl TransferCurve {
+    pub fn new(points: Vec<f32>, size: Vec2) -> Self {
+        Self {
+            points,
+            size,
+            curve_color: Color32::GREEN,
+            reference_color: Color32::GRAY,
+            background_color: Color32::BLACK,
+            border_color: Color32::WHITE,
+        }
+    }
+
+    /// Take all colors from a theme
+    pub fn set_theme(mut self, theme: &Theme) -> Self {
+        self.curve_color = theme.primary;
+        self.reference_color = theme.secondary.linear_multiply(0.4);
+        self.background_color = theme.panel;
+        self.border_color = theme.outline;
+        self
+    }
+}
+
+impl Widget for TransferCurve {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let (rect, response) = ui.allocate_exact_size(self.size, Sense::hover());
+        if ui.is_rect_visible(rect) {
+            let painter = ui.painter_at(rect);
+            painter.rect(rect, Rounding::from(4.0), self.background_color, Stroke::new(1.0, self.border_color));
+
+            // Some algorithms go well past unity so fit those in
+            let range = self.points.iter().fold(1.0_f32, |max, point| max.max(point.abs())).min(8.0);
+            let to_screen = |x: f32, y: f32| -> Pos2 {
+                pos2(
+                    rect.center().x + x * rect.width() / 2.0,
+                    rect.center().y - (y / range) * rect.height() / 2.0,
+                )
+            };
+
+            // Axes and the unprocessed straight line for reference
+            let reference = Stroke::new(1.0, self.reference_color);
+            painter.line_segment([pos2(rect.left(), rect.center().y), pos2(rect.right(), rect.center().y)], reference);
+            painter.line_segment([pos2(rect.center().x, rect.top()), pos2(rect.center().x, rect.bottom())], reference);
+            painter.line_segment([to_screen(-1.0, -1.0), to_screen(1.0, 1.0)], reference);
+
+            if self.points.len() > 1 {
+                let last = (self.points.len() - 1) as f32;
+                let curve: Vec<Pos2> = self
+                    .points
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, y)| to_screen(idx as f32 / last * 2.0 - 1.0, y.clamp(-range, range)))
+                    .collect();
+                painter.add(Shape::line(curve, Stroke::new(2.0, self.curve_color)));
+            }
+        }
+        response
+    }
+}
+
+#[must_use = "You should put this widget in an ui with `ui.add(widget);`"]
+pub struct Scope {
+    input: Vec<f32>,
+    output: Vec<f32>,
+    size: Vec2,
+    input_color: Color32,
+    output_color: Color32,
+    background_color: Color32,
+    border_color: Color32,
+}
+
+#[allow(dead_code)]
+impl Scope {
+    pub fn new(input: Vec<f32>, output: Vec<f32>, size: Vec2) -> Self {
+        Self {
+            input,
+            output,
+            size,
+            input_color: Color32::GRAY,
+            output_color: Color32::GREEN,
+            background_color: Color32::BLACK,
+            border_color: Color32::WHITE,
+        }
+    }
+
+    /// Take all colors from a theme
+    pub fn set_theme(mut self, theme: &Theme) -> Self {
+        self.input_color = theme.secondary;
+        self.output_color = theme.primary;
+        self.background_color = theme.panel;
+        self.border_color = theme.outline;
+        self
+    }
+}
+
+impl Widget for Scope {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let (rect, response) = ui.allocate_exact_size(self.size, Sense::hover());
+        if ui.is_rect_visible(rect) {
+            let painter = ui.painter_at(rect);
+            painter.rect(rect, Rounding::from(4.0), self.background_color, Stroke::new(1.0, self.border_color));
+            painter.line_segment(
+                [pos2(rect.left(), rect.center().y), pos2(rect.right(), rect.center().y)],
+                Stroke::new(1.0, self.border_color.linear_multiply(0.5)),
+            );
+
+            // Both traces share a scale so the level change is visible
+            let range = self
+                .input
+                .iter()
+                .chain(self.output.iter())
+                .fold(0.01_f32, |max, sample| max.max(sample.abs()));
+            let to_points = |samples: &[f32]| -> Vec<Pos2> {
+                let last = (samples.len().max(2) - 1) as f32;
+                samples
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, sample)| {
+                        pos2(
+                            rect.left() + idx as f32 / last * rect.width(),
+                            rect.center().y - (sample / range) * rect.height() / 2.0,
+                        )
+                    })
+                    .collect()
+            };
+            painter.add(Shape::line(to_points(&self.input), Stroke::new(1.0, self.input_color)));
+            painter.add(Shape::line(to_points(&self.output), Stroke::new(1.5, self.output_color)));
+        }
+        response
+    }
+}
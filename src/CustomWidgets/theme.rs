@@ -0,0 +1,153 @@
+// theme.rs - Ardura 2024
+// Colors every widget in the editor reads from, with built in themes and user themes from TOML
+
+use nih_plug_egui::egui::Color32;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_THEME: &str = "Classic";
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    // Main panel behind everything
+    #[serde(with = "rgb")]
+    pub background: Color32,
+    // Knob centers, meter and drawbar backgrounds
+    #[serde(with = "rgb")]
+    pub panel: Color32,
+    // Main knob arcs, meter bars and text
+    #[serde(with = "rgb")]
+    pub primary: Color32,
+    // Second knob column and alternate highlights
+    #[serde(with = "rgb")]
+    pub secondary: Color32,
+    // Borders, meter text and outlines
+    #[serde(with = "rgb")]
+    pub outline: Color32,
+    #[serde(with = "rgb")]
+    pub screws: Color32,
+}
+
+// What a user theme file looks like:
+//
+// [[theme]]
+// name = "Mine"
+// background = [55, 50, 48]
+// panel = [13, 62, 102]
+// primary = [239, 141, 11]
+// secondary = [204, 205, 196]
+// outline = [0, 0, 0]
+// screws = [96, 96, 96]
+#[derive(Deserialize)]
+struct ThemeFile {
+    #[serde(default)]
+    theme: Vec<Theme>,
+}
+
+impl Theme {
+    // The original Subhoofer look
+    pub fn classic() -> Self {
+        Self {
+            name: DEFAULT_THEME.to_string(),
+            background: Color32::from_rgb(55, 50, 48),
+            panel: Color32::from_rgb(13, 62, 102),
+            primary: Color32::from_rgb(239, 141, 11),
+            secondary: Color32::from_rgb(204, 205, 196),
+            outline: Color32::BLACK,
+            screws: Color32::DARK_GRAY,
+        }
+    }
+
+    pub fn dark() -> Self {
+        Self {
+            name: "Dark".to_string(),
+            background: Color32::from_rgb(18, 18, 20),
+            panel: Color32::from_rgb(40, 40, 46),
+            primary: Color32::from_rgb(120, 170, 255),
+            secondary: Color32::from_rgb(150, 150, 160),
+            outline: Color32::from_rgb(5, 5, 5),
+            screws: Color32::from_rgb(60, 60, 64),
+        }
+    }
+
+    // Pure black and white with bright accents for accessibility
+    pub fn high_contrast() -> Self {
+        Self {
+            name: "High Contrast".to_string(),
+            background: Color32::BLACK,
+            panel: Color32::from_rgb(20, 20, 20),
+            primary: Color32::YELLOW,
+            secondary: Color32::WHITE,
+            outline: Color32::WHITE,
+            screws: Color32::GRAY,
+        }
+    }
+
+    pub fn built_in() -> Vec<Self> {
+        vec![Self::classic(), Self::dark(), Self::high_contrast()]
+    }
+
+    // Built in themes followed by any from the user's theme file
+    pub fn all() -> Vec<Self> {
+        let mut themes = Self::built_in();
+        if let Some(path) = user_theme_path() {
+            for theme in load_themes(&path) {
+                // Don't let a user theme shadow a built in name
+                if !themes.iter().any(|existing| existing.name == theme.name) {
+                    themes.push(theme);
+                }
+            }
+        }
+        themes
+    }
+
+    // Find a theme by name, falling back to Classic
+    pub fn find(themes: &[Self], name: &str) -> Self {
+        themes
+            .iter()
+            .find(|theme| theme.name == name)
+            .cloned()
+            .unwrap_or_else(Self::classic)
+    }
+}
+
+// Bad or missing files just mean no user themes
+pub fn load_themes(path: &Path) -> Vec<Theme> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => match toml::from_str::<ThemeFile>(&contents) {
+            Ok(file) => file.theme,
+            Err(_) => Vec::new(),
+        },
+        Err(_) => Vec::new(),
+    }
+}
+
+// themes.toml in the platform config directory under Subhoofer
+pub fn user_theme_path() -> Option<PathBuf> {
+    let config_dir = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library").join("Application Support"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    config_dir.map(|dir| dir.join("Subhoofer").join("themes.toml"))
+}
+
+// Colors are stored as [r, g, b] in theme files
+mod rgb {
+    use nih_plug_egui::egui::Color32;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color32, serializer: S) -> Result<S::Ok, S::Error> {
+        [color.r(), color.g(), color.b()].serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color32, D::Error> {
+        let [r, g, b] = <[u8; 3]>::deserialize(deserializer)?;
+        Ok(Color32::from_rgb(r, g, b))
+    }
+}
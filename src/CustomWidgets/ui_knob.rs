@@ -8,9 +8,10 @@ use std::{
 };
 
 use lazy_static::lazy_static;
+use super::theme::Theme;
 use nih_plug::prelude::{Param, ParamSetter};
 use nih_plug_egui::egui::{
-    self, epaint::{CircleShape, PathShape}, pos2, Align2, Color32, FontId, Pos2, Rect, Response, Rgba, Rounding, Sense, Shape, Stroke, Ui, Vec2, Widget
+    self, epaint::{CircleShape, PathShape}, pos2, Align2, Button, Color32, FontId, Key, Pos2, Rect, Response, Rgba, Rounding, Sense, Shape, Stroke, TextEdit, Ui, Vec2, Widget
 };
 
 /// When shift+dragging a parameter, one pixel dragged corresponds to this much change in the
@@ -18,11 +19,16 @@ use nih_plug_egui::egui::{
 const GRANULAR_DRAG_MULTIPLIER: f32 = 0.001;
 const NORMAL_DRAG_MULTIPLIER: f32 = 0.005;
 
+/// How far one mouse wheel notch or arrow key press moves a continuous parameter, and with shift held
+const NUDGE_AMOUNT: f32 = 0.01;
+const GRANULAR_NUDGE_AMOUNT: f32 = 0.001;
+
 lazy_static! {
     //static ref DRAG_NORMALIZED_START_VALUE_MEMORY_ID: egui::Id = egui::Id::new((file!(), rand::random::<i64>()));
     static ref DRAG_NORMALIZED_START_VALUE_MEMORY_ID: egui::Id = egui::Id::new((file!(), 0));
     static ref DRAG_AMOUNT_MEMORY_ID: egui::Id = egui::Id::new((file!(), 1));
     static ref VALUE_ENTRY_MEMORY_ID: egui::Id = egui::Id::new((file!(), 2));
+    static ref CLIPBOARD_MEMORY_ID: egui::Id = egui::Id::new((file!(), 3));
 }
 
 struct SliderRegion<'a, P: Param> {
@@ -160,6 +166,133 @@ impl<'a, P: Param> SliderRegion<'a, P> {
     fn get_string(&self) -> String {
         self.param.to_string()
     }
+
+    // The value without its unit so it can be typed over or pasted elsewhere
+    fn get_entry_string(&self) -> String {
+        self.param.normalized_value_to_string(self.normalized_value(), false)
+    }
+
+    // Parse typed or pasted text through the param's own string_to_value
+    fn set_from_string(&self, text: &str) -> bool {
+        match self.param.string_to_normalized_value(text) {
+            Some(normalized) => {
+                self.param_setter.begin_set_parameter(self.param);
+                self.set_normalized_value(normalized);
+                self.param_setter.end_set_parameter(self.param);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Reset as its own gesture for the context menu
+    fn reset_with_gesture(&self) {
+        self.param_setter.begin_set_parameter(self.param);
+        self.reset_param();
+        self.param_setter.end_set_parameter(self.param);
+    }
+
+    // Stepped parameters like the algorithm move one step at a time
+    fn nudge(&self, direction: f32, granular: bool) {
+        let amount = match self.param.step_count() {
+            Some(steps) => 1.0 / steps as f32,
+            None if granular => GRANULAR_NUDGE_AMOUNT,
+            None => NUDGE_AMOUNT,
+        };
+        self.param_setter.begin_set_parameter(self.param);
+        self.set_normalized_value((self.normalized_value() + direction * amount).clamp(0.0, 1.0));
+        self.param_setter.end_set_parameter(self.param);
+    }
+
+    // Mouse wheel while hovered and arrow keys while focused
+    fn handle_nudges(&self, ui: &Ui, response: &mut Response) {
+        if response.clicked() || response.drag_started() {
+            response.request_focus();
+        }
+        let granular = ui.input(|i| i.modifiers.shift);
+        if response.hovered() {
+            let scroll = ui.input(|i| i.raw_scroll_delta.y);
+            if scroll != 0.0 {
+                self.nudge(scroll.signum(), granular);
+                response.mark_changed();
+            }
+        }
+        if response.has_focus() {
+            let (up, down) = ui.input(|i| {
+                (
+                    i.key_pressed(Key::ArrowUp) || i.key_pressed(Key::ArrowRight),
+                    i.key_pressed(Key::ArrowDown) || i.key_pressed(Key::ArrowLeft),
+                )
+            });
+            if up {
+                self.nudge(1.0, granular);
+                response.mark_changed();
+            }
+            if down {
+                self.nudge(-1.0, granular);
+                response.mark_changed();
+            }
+        }
+    }
+
+    // Ctrl/Cmd+V while hovered or focused pastes from the system clipboard
+    fn handle_paste(&self, ui: &Ui, response: &mut Response) {
+        if !(response.hovered() || response.has_focus()) {
+            return;
+        }
+        if let Some(text) = pasted_text(ui) {
+            if self.set_from_string(&text) {
+                response.mark_changed();
+            }
+        }
+    }
+
+    // Right click menu with reset, copy and paste. egui only sees the system clipboard through a
+    // paste event, so Ctrl/Cmd+V with the menu open pastes from it and the button falls back to
+    // the last value copied from any knob
+    fn context_menu(&self, response: &mut Response) {
+        let mut changed = false;
+        let _ = response.clone().context_menu(|ui| {
+            if ui.button("Reset").clicked() {
+                self.reset_with_gesture();
+                changed = true;
+                ui.close_menu();
+            }
+            if ui.button("Copy value").clicked() {
+                let text = self.get_entry_string();
+                ui.output_mut(|output| output.copied_text = text.clone());
+                ui.memory_mut(|mem| mem.data.insert_temp(*CLIPBOARD_MEMORY_ID, text));
+                ui.close_menu();
+            }
+            if let Some(text) = pasted_text(ui) {
+                changed = self.set_from_string(&text);
+                ui.close_menu();
+            }
+            let copied: Option<String> = ui.memory(|mem| mem.data.get_temp(*CLIPBOARD_MEMORY_ID));
+            if ui.add_enabled(copied.is_some(), Button::new("Paste value"))
+                .on_hover_text("Ctrl/Cmd+V pastes from the clipboard")
+                .clicked()
+            {
+                if let Some(text) = copied {
+                    changed = self.set_from_string(&text);
+                }
+                ui.close_menu();
+            }
+        });
+        if changed {
+            response.mark_changed();
+        }
+    }
+}
+
+// Text from a paste event this frame, if there was one
+fn pasted_text(ui: &Ui) -> Option<String> {
+    ui.input(|i| {
+        i.events.iter().rev().find_map(|event| match event {
+            egui::Event::Paste(text) => Some(text.trim().to_string()),
+            _ => None,
+        })
+    })
 }
 
 pub struct ArcKnob<'a, P: Param> {
@@ -295,6 +428,13 @@ impl<'a, P: Param> ArcKnob<'a, P> {
         self
     }
 
+    // Take fill and line colors from a theme
+    pub fn set_theme(mut self, theme: &Theme) -> Self {
+        self.fill_color = theme.panel;
+        self.line_color = theme.primary;
+        self
+    }
+
     // Specify fill color for knob
     pub fn set_fill_color(mut self, new_color: Color32) -> Self {
         self.fill_color = new_color;
@@ -363,6 +503,76 @@ impl<'a, P: Param> ArcKnob<'a, P> {
         }
         self
     }
+
+    // Where the label and value text sit for the layout
+    fn label_and_value_pos(&self, rect: Rect) -> (Pos2, Pos2) {
+        let label_y = if self.padding == 0.0 {
+            6.0
+        } else {
+            self.padding * 2.0
+        };
+        let value_pos: Pos2;
+        let label_pos: Pos2;
+        match self.layout {
+            KnobLayout::SquareNoLabel => {
+                // This isn't indended to be a possibility but it has to be here since
+                // it's in the enum. Hence these making no sense
+                value_pos = rect.center();
+                label_pos = rect.center();
+            },
+            KnobLayout::Default => {
+                if self.swap_label_and_value {
+                    // Newer rearranged positions to put value at bottom of knob
+                    value_pos = Pos2::new(
+                        rect.center_bottom().x,
+                        rect.center_bottom().y - label_y,
+                    );
+                    label_pos =
+                        Pos2::new(rect.center().x, rect.center().y);
+                } else {
+                    // The old value and label positions
+                    label_pos = Pos2::new(
+                        rect.center_bottom().x,
+                        rect.center_bottom().y - label_y,
+                    );
+                    value_pos =
+                        Pos2::new(rect.center().x, rect.center().y);
+                }
+            }
+            // GUI Rewrite for Actuate made these
+            KnobLayout::Vertical => {
+                label_pos = Pos2::new(
+                    rect.center_top().x,
+                    rect.center_top().y + label_y * 1.5,
+                );
+                value_pos = Pos2::new(
+                    rect.center_bottom().x,
+                    rect.center_bottom().y - label_y * 1.5,
+                );
+            }
+            KnobLayout::Horizonal => {
+                label_pos = Pos2::new(
+                    rect.center().x + self.radius / 1.5,
+                    rect.right_center().y - label_y,
+                );
+                value_pos = Pos2::new(
+                    rect.center().x + self.radius / 1.5,
+                    rect.right_center().y + label_y,
+                );
+            }
+            KnobLayout::HorizontalInline => {
+                label_pos = Pos2::new(
+                    rect.center().x + self.radius / 1.5,
+                    rect.right_center().y,
+                );
+                value_pos = Pos2::new(
+                    rect.center().x + self.radius / 1.5,
+                    rect.right_center().y,
+                );
+            }
+        }
+        (label_pos, value_pos)
+    }
 }
 
 impl<'a, P: Param> Widget for ArcKnob<'a, P> {
@@ -393,6 +603,51 @@ impl<'a, P: Param> Widget for ArcKnob<'a, P> {
 
         let mut response = ui.allocate_response(desired_size, Sense::click_and_drag());
         let value = self.slider_region.handle_response(&ui, &mut response);
+        self.slider_region.handle_nudges(ui, &mut response);
+        self.slider_region.context_menu(&mut response);
+
+        // Clicking the value text lets you type a new one in
+        let (label_pos, value_pos) = self.label_and_value_pos(response.rect);
+        let value_rect = Rect::from_center_size(value_pos, Vec2::new(self.radius * 2.0, self.text_size * 1.5));
+        let entry_id = response.id.with(*VALUE_ENTRY_MEMORY_ID);
+        let can_type = self.show_label
+            && matches!(self.layout, KnobLayout::Default | KnobLayout::Vertical | KnobLayout::Horizonal);
+        let mut editing = can_type && ui.memory(|mem| mem.has_focus(entry_id));
+        // Pasting into the value entry is left to the text box
+        if !editing {
+            self.slider_region.handle_paste(ui, &mut response);
+        }
+        if editing {
+            let mut entry_text: String = ui.memory(|mem| mem.data.get_temp(*VALUE_ENTRY_MEMORY_ID)).unwrap_or_default();
+            let mut entry_ui = ui.child_ui(value_rect, *ui.layout());
+            entry_ui.add(
+                TextEdit::singleline(&mut entry_text)
+                    .id(entry_id)
+                    .font(FontId::proportional(self.text_size))
+                    .desired_width(value_rect.width()),
+            );
+            if ui.input(|i| i.key_pressed(Key::Escape)) {
+                ui.memory_mut(|mem| mem.surrender_focus(entry_id));
+                editing = false;
+            } else if ui.input(|i| i.key_pressed(Key::Enter)) {
+                if self.slider_region.set_from_string(&entry_text) {
+                    response.mark_changed();
+                }
+                ui.memory_mut(|mem| mem.surrender_focus(entry_id));
+                editing = false;
+            }
+            ui.memory_mut(|mem| mem.data.insert_temp(*VALUE_ENTRY_MEMORY_ID, entry_text));
+        } else if can_type
+            && response.clicked()
+            && ui.input(|i| i.pointer.interact_pos()).map_or(false, |pos| value_rect.contains(pos))
+        {
+            let entry_text = self.slider_region.get_entry_string();
+            ui.memory_mut(|mem| {
+                mem.data.insert_temp(*VALUE_ENTRY_MEMORY_ID, entry_text);
+                mem.request_focus(entry_id);
+            });
+            editing = true;
+        }
 
         ui.vertical(|ui| {
             let painter = ui.painter_at(response.rect);
@@ -744,73 +999,7 @@ impl<'a, P: Param> Widget for ArcKnob<'a, P> {
             }
 
             // Label text from response rect bound
-            let label_y = if self.padding == 0.0 {
-                6.0
-            } else {
-                self.padding * 2.0
-            };
             if self.show_label {
-                let value_pos: Pos2;
-                let label_pos: Pos2;
-                match self.layout {
-                    KnobLayout::SquareNoLabel => {
-                        // This isn't indended to be a possibility but it has to be here since
-                        // it's in the enum. Hence these making no sense
-                        value_pos = response.rect.center();
-                        label_pos = response.rect.center();
-                    },
-                    KnobLayout::Default => {
-                        if self.swap_label_and_value {
-                            // Newer rearranged positions to put value at bottom of knob
-                            value_pos = Pos2::new(
-                                response.rect.center_bottom().x,
-                                response.rect.center_bottom().y - label_y,
-                            );
-                            label_pos =
-                                Pos2::new(response.rect.center().x, response.rect.center().y);
-                        } else {
-                            // The old value and label positions
-                            label_pos = Pos2::new(
-                                response.rect.center_bottom().x,
-                                response.rect.center_bottom().y - label_y,
-                            );
-                            value_pos =
-                                Pos2::new(response.rect.center().x, response.rect.center().y);
-                        }
-                    }
-                    // GUI Rewrite for Actuate made these
-                    KnobLayout::Vertical => {
-                        label_pos = Pos2::new(
-                            response.rect.center_top().x,
-                            response.rect.center_top().y + label_y * 1.5,
-                        );
-                        value_pos = Pos2::new(
-                            response.rect.center_bottom().x,
-                            response.rect.center_bottom().y - label_y * 1.5,
-                        );
-                    }
-                    KnobLayout::Horizonal => {
-                        label_pos = Pos2::new(
-                            response.rect.center().x + self.radius / 1.5,
-                            response.rect.right_center().y - label_y,
-                        );
-                        value_pos = Pos2::new(
-                            response.rect.center().x + self.radius / 1.5,
-                            response.rect.right_center().y + label_y,
-                        );
-                    }
-                    KnobLayout::HorizontalInline => {
-                        label_pos = Pos2::new(
-                            response.rect.center().x + self.radius / 1.5,
-                            response.rect.right_center().y,
-                        );
-                        value_pos = Pos2::new(
-                            response.rect.center().x + self.radius / 1.5,
-                            response.rect.right_center().y,
-                        );
-                    }
-                }
-
                 if self.readable_box {
                     // Background for text readability
                     let readability_box = Rect::from_two_pos(
@@ -856,20 +1045,23 @@ impl<'a, P: Param> Widget for ArcKnob<'a, P> {
                             text_color.linear_multiply(0.4),
                         );
                     } else {
-                        painter.text(
-                            value_pos,
-                            Align2::CENTER_CENTER,
-                            self.slider_region.get_string(),
-                            FontId::proportional(self.text_size),
-                            Color32::WHITE.linear_multiply(0.1),
-                        );
-                        painter.text(
-                            value_pos,
-                            Align2::CENTER_CENTER,
-                            self.slider_region.get_string(),
-                            FontId::proportional(self.text_size),
-                            text_color,
-                        );
+                        // The text entry box sits here while typing
+                        if !editing {
+                            painter.text(
+                                value_pos,
+                                Align2::CENTER_CENTER,
+                                self.slider_region.get_string(),
+                                FontId::proportional(self.text_size),
+                                Color32::WHITE.linear_multiply(0.1),
+                            );
+                            painter.text(
+                                value_pos,
+                                Align2::CENTER_CENTER,
+                                self.slider_region.get_string(),
+                                FontId::proportional(self.text_size),
+                                text_color,
+                            );
+                        }
                         painter.text(
                             label_pos,
                             Align2::CENTER_CENTER,
@@ -902,20 +1094,23 @@ impl<'a, P: Param> Widget for ArcKnob<'a, P> {
                             text_color.linear_multiply(0.4),
                         );
                     } else {
-                        painter.text(
-                            value_pos,
-                            Align2::CENTER_CENTER,
-                            self.label_text.to_string(),
-                            FontId::proportional(self.text_size),
-                            Color32::WHITE.linear_multiply(0.1),
-                        );
-                        painter.text(
-                            value_pos,
-                            Align2::CENTER_CENTER,
-                            self.label_text,
-                            FontId::proportional(self.text_size),
-                            text_color,
-                        );
+                        // The text entry box sits here while typing
+                        if !editing {
+                            painter.text(
+                                value_pos,
+                                Align2::CENTER_CENTER,
+                                self.label_text.to_string(),
+                                FontId::proportional(self.text_size),
+                                Color32::WHITE.linear_multiply(0.1),
+                            );
+                            painter.text(
+                                value_pos,
+                                Align2::CENTER_CENTER,
+                                self.label_text,
+                                FontId::proportional(self.text_size),
+                                text_color,
+                            );
+                        }
                         painter.text(
                             label_pos,
                             Align2::CENTER_CENTER,
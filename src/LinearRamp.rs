@@ -0,0 +1,53 @@
+// Linear parameter smoothing where the ramp time can change while running
+// Used for the knobs that follow the "Smoothing Time" setting
+// Ardura
+
+#[derive(Clone, Copy)]
+pub struct LinearRamp {
+    current: f32,
+    target: f32,
+    step: f32,
+    steps_left: u32,
+}
+
+impl LinearRamp {
+    pub fn new(value: f32) -> Self {
+        Self {
+            current: value,
+            target: value,
+            step: 0.0,
+            steps_left: 0,
+        }
+    }
+
+    // Jump straight to a value
+    pub fn reset(&mut self, value: f32) {
+        *self = Self::new(value);
+    }
+
+    // Move one sample toward the target, a new target restarts the ramp from where we are
+    pub fn next(&mut self, target: f32, ramp_samples: u32) -> f32 {
+        if target != self.target {
+            self.target = target;
+            if ramp_samples == 0 {
+                self.current = target;
+                self.steps_left = 0;
+            } else {
+                self.step = (target - self.current) / ramp_samples as f32;
+                self.steps_left = ramp_samples;
+            }
+        }
+        if self.steps_left > 0 {
+            self.steps_left -= 1;
+            self.current = if self.steps_left == 0 { self.target } else { self.current + self.step };
+        }
+        self.current
+    }
+
+    // Fill a whole block of values at once, same as calling next() for each
+    pub fn next_block(&mut self, values: &mut [f32], target: f32, ramp_samples: u32) {
+        for value in values.iter_mut() {
+            *value = self.next(target, ramp_samples);
+        }
+    }
+}
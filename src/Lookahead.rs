@@ -0,0 +1,71 @@
+// Delays the main channels so the sub generator can hear notes before they play
+// Ardura
+
+pub const MAX_LOOKAHEAD_MS: f32 = 20.0;
+
+// A new delay fades in from the old one over this long instead of jumping
+const DELAY_FADE_MS: f32 = 10.0;
+
+pub struct Lookahead {
+    buffers: Vec<Vec<f32>>,
+    write: usize,
+    delay: usize,
+    // The delay being faded away from and how much of the fade is left
+    fade_from: usize,
+    fade_length: usize,
+    fade_left: usize,
+}
+
+impl Lookahead {
+    pub fn new() -> Self {
+        Self { buffers: Vec::new(), write: 0, delay: 0, fade_from: 0, fade_length: 1, fade_left: 0 }
+    }
+
+    // Allocates room for the longest lookahead, call from initialize and not while processing
+    pub fn resize(&mut self, num_channels: usize, sample_rate: f32) {
+        let length: usize = (MAX_LOOKAHEAD_MS * 0.001 * sample_rate).ceil() as usize + 1;
+        self.buffers = vec![vec![0.0; length]; num_channels];
+        self.write = 0;
+        self.fade_length = ((DELAY_FADE_MS * 0.001 * sample_rate) as usize).max(1);
+        self.fade_left = 0;
+    }
+
+    pub fn delay(&self) -> usize {
+        self.delay
+    }
+
+    pub fn set_delay(&mut self, samples: usize) {
+        let length: usize = self.buffers.first().map_or(1, |buffer| buffer.len());
+        let delay: usize = samples.min(length - 1);
+        if delay != self.delay {
+            self.fade_from = self.delay;
+            self.fade_left = self.fade_length;
+            self.delay = delay;
+        }
+    }
+
+    // Swaps each channel for itself `delay` samples ago. The history is always written so a
+    // longer delay has real audio to fade into
+    pub fn process(&mut self, channels: &mut [&mut [f32]], block_len: usize) {
+        let Some(length) = self.buffers.first().map(|buffer| buffer.len()) else { return; };
+        for (buffer, channel) in self.buffers.iter_mut().zip(channels.iter_mut()) {
+            let mut write: usize = self.write;
+            let mut fade_left: usize = self.fade_left;
+            for sample in channel[..block_len].iter_mut() {
+                buffer[write] = *sample;
+                let delayed: f32 = buffer[(write + length - self.delay) % length];
+                *sample = if fade_left > 0 {
+                    let old: f32 = buffer[(write + length - self.fade_from) % length];
+                    let amount: f32 = fade_left as f32 / self.fade_length as f32;
+                    fade_left -= 1;
+                    delayed + (old - delayed) * amount
+                } else {
+                    delayed
+                };
+                write = (write + 1) % length;
+            }
+        }
+        self.write = (self.write + block_len) % length;
+        self.fade_left = self.fade_left.saturating_sub(block_len);
+    }
+}
@@ -0,0 +1,250 @@
+// LFOs and an envelope follower for the mod matrix, and the params for both.
+// Dynamic Harmonics uses the same envelope follower
+// Ardura
+
+use nih_plug::prelude::*;
+use std::f32::consts::PI;
+
+pub const NUM_LFOS: usize = 2;
+pub const NUM_SLOTS: usize = 4;
+pub const NUM_TARGETS: usize = 5;
+
+// Below this the envelope reads as 0
+const ENVELOPE_FLOOR_DB: f32 = -60.0;
+
+#[derive(Enum, PartialEq, Eq, Debug, Copy, Clone)]
+pub enum LfoShape {
+    #[name = "Sine"]
+    Sine,
+    #[name = "Triangle"]
+    Triangle,
+    #[name = "Saw"]
+    Saw,
+    #[name = "Square"]
+    Square,
+}
+
+#[derive(Enum, PartialEq, Eq, Debug, Copy, Clone)]
+pub enum SyncDivision {
+    #[name = "4 Bars"]
+    FourBars,
+    #[name = "2 Bars"]
+    TwoBars,
+    #[name = "1 Bar"]
+    OneBar,
+    #[name = "1/2"]
+    Half,
+    #[name = "1/4"]
+    Quarter,
+    #[name = "1/8"]
+    Eighth,
+    #[name = "1/8 T"]
+    EighthTriplet,
+    #[name = "1/16"]
+    Sixteenth,
+}
+
+impl SyncDivision {
+    // Length of one LFO cycle in quarter notes
+    pub fn beats(self) -> f64 {
+        match self {
+            SyncDivision::FourBars => 16.0,
+            SyncDivision::TwoBars => 8.0,
+            SyncDivision::OneBar => 4.0,
+            SyncDivision::Half => 2.0,
+            SyncDivision::Quarter => 1.0,
+            SyncDivision::Eighth => 0.5,
+            SyncDivision::EighthTriplet => 1.0 / 3.0,
+            SyncDivision::Sixteenth => 0.25,
+        }
+    }
+}
+
+#[derive(Enum, PartialEq, Eq, Debug, Copy, Clone)]
+pub enum ModSource {
+    #[name = "Off"]
+    Off,
+    #[name = "LFO 1"]
+    Lfo1,
+    #[name = "LFO 2"]
+    Lfo2,
+    #[name = "Envelope"]
+    Envelope,
+}
+
+// The order here is the index into the per target offsets
+#[derive(Enum, PartialEq, Eq, Debug, Copy, Clone)]
+pub enum ModTarget {
+    #[name = "Harmonics"]
+    Harmonics,
+    #[name = "Hardness"]
+    Hardness,
+    #[name = "Sub Gain"]
+    SubGain,
+    #[name = "Sub Drive"]
+    SubDrive,
+    #[name = "Dry/Wet"]
+    DryWet,
+}
+
+#[derive(Params)]
+pub struct LfoParams {
+    #[id = "LFO Shape"]
+    pub shape: EnumParam<LfoShape>,
+
+    #[id = "LFO Rate"]
+    pub rate: FloatParam,
+
+    #[id = "LFO Sync"]
+    pub sync: BoolParam,
+
+    #[id = "LFO Division"]
+    pub division: EnumParam<SyncDivision>,
+}
+
+impl LfoParams {
+    pub fn new(number: usize) -> Self {
+        Self {
+            shape: EnumParam::new(format!("LFO {number} Shape"), LfoShape::Sine),
+
+            rate: FloatParam::new(
+                format!("LFO {number} Rate"),
+                1.0,
+                FloatRange::Skewed {
+                    min: 0.01,
+                    max: 20.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_rounded(2)),
+
+            // Follow the host tempo and position instead of the rate
+            sync: BoolParam::new(format!("LFO {number} Sync"), false),
+
+            division: EnumParam::new(format!("LFO {number} Division"), SyncDivision::Quarter),
+        }
+    }
+}
+
+// One row of the mod matrix
+#[derive(Params)]
+pub struct ModSlotParams {
+    #[id = "Mod Source"]
+    pub source: EnumParam<ModSource>,
+
+    #[id = "Mod Target"]
+    pub target: EnumParam<ModTarget>,
+
+    #[id = "Mod Depth"]
+    pub depth: FloatParam,
+}
+
+impl ModSlotParams {
+    pub fn new(number: usize) -> Self {
+        Self {
+            source: EnumParam::new(format!("Mod {number} Source"), ModSource::Off),
+
+            target: EnumParam::new(format!("Mod {number} Target"), ModTarget::Harmonics),
+
+            // How far the source moves the target knob, as a share of its whole range
+            depth: FloatParam::new(
+                format!("Mod {number} Depth"),
+                0.0,
+                FloatRange::Linear { min: -1.0, max: 1.0 },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+        }
+    }
+}
+
+// Tempo and position from the host, moved along by the plugin between process calls
+#[derive(Clone, Copy)]
+pub struct HostClock {
+    pub tempo: f64,
+    // Quarter notes from the start of the song, only while the host is playing
+    pub beats: Option<f64>,
+}
+
+impl HostClock {
+    pub fn new() -> Self {
+        Self { tempo: 120.0, beats: None }
+    }
+
+    pub fn beats_per_sample(&self, sample_rate: f32) -> f64 {
+        self.tempo / 60.0 / sample_rate as f64
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Lfo {
+    phase: f64,
+}
+
+impl Lfo {
+    pub fn new() -> Self {
+        Self { phase: 0.0 }
+    }
+
+    // Bipolar output for one sample. Synced LFOs lock to `beats` when the host gives a position
+    pub fn next(&mut self, params: &LfoParams, clock: &HostClock, beats: Option<f64>, sample_rate: f32) -> f32 {
+        let cycle_beats: f64 = params.division.value().beats();
+        match (params.sync.value(), beats) {
+            (true, Some(beats)) => self.phase = (beats / cycle_beats).rem_euclid(1.0),
+            (true, None) => self.phase = (self.phase + clock.tempo / 60.0 / cycle_beats / sample_rate as f64).fract(),
+            (false, _) => self.phase = (self.phase + params.rate.value() as f64 / sample_rate as f64).fract(),
+        }
+
+        let phase: f32 = self.phase as f32;
+        match params.shape.value() {
+            LfoShape::Sine => (2.0 * PI * phase).sin(),
+            LfoShape::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            LfoShape::Saw => 2.0 * phase - 1.0,
+            LfoShape::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct EnvelopeFollower {
+    envelope: f32,
+    attack: f32,
+    release: f32,
+}
+
+impl EnvelopeFollower {
+    pub fn new() -> Self {
+        Self { envelope: 0.0, attack: 0.0, release: 0.0 }
+    }
+
+    pub fn set(&mut self, attack_ms: f32, release_ms: f32, sample_rate: f32) {
+        self.attack = (-1.0 / (attack_ms * 0.001 * sample_rate)).exp();
+        self.release = (-1.0 / (release_ms * 0.001 * sample_rate)).exp();
+    }
+
+    pub fn reset(&mut self) {
+        self.envelope = 0.0;
+    }
+
+    // The envelope in dB, for Dynamic Harmonics
+    pub fn next_db(&mut self, peak: f32) -> f32 {
+        let coefficient: f32 = if peak > self.envelope { self.attack } else { self.release };
+        self.envelope = peak + (self.envelope - peak) * coefficient;
+        util::gain_to_db_fast(self.envelope)
+    }
+
+    // 0 to 1 over the last 60 dB below full scale
+    pub fn next(&mut self, peak: f32) -> f32 {
+        ((self.next_db(peak) - ENVELOPE_FLOOR_DB) / -ENVELOPE_FLOOR_DB).clamp(0.0, 1.0)
+    }
+}
+
+// Moves a knob value by `offset` of its normalized range
+pub fn modulate(param: &FloatParam, value: f32, offset: f32) -> f32 {
+    if offset == 0.0 {
+        return value;
+    }
+    param.preview_plain((param.preview_normalized(value) + offset).clamp(0.0, 1.0))
+}
@@ -0,0 +1,156 @@
+// Brings sessions saved by older versions of Subhoofer up to date before they're loaded, so an
+// update never changes the sound of a finished mix
+// Ardura
+
+use crate::{AlgorithmType, HEIGHT, WIDTH};
+use nih_plug::params::persist::PersistentField;
+use nih_plug::prelude::*;
+use nih_plug::wrapper::state::ParamValue;
+use nih_plug_egui::EguiState;
+
+// Bump this and add a step to MIGRATIONS whenever the DSP or a parameter changes in a way that
+// would make an old session sound different
+pub const STATE_VERSION: u32 = 2;
+
+// Where the version is kept in the saved state, matches the #[persist] key
+pub const STATE_VERSION_KEY: &str = "state-version";
+
+// Where the editor size is kept in the saved state, matches the #[persist] key
+const EDITOR_STATE_KEY: &str = "editor-state";
+
+// The editor was this tall before the layout grew to HEIGHT
+const LEGACY_HEIGHT: u32 = 528;
+
+// Step N takes a session saved at version N to version N + 1
+const MIGRATIONS: [fn(&mut PluginState); STATE_VERSION as usize] = [
+    legacy_abass2,
+    legacy_sub_gate,
+];
+
+pub fn migrate(state: &mut PluginState) {
+    // Sessions from before the version was saved are version 0. Persisted fields are stored as JSON
+    let saved_version: u32 = state
+        .fields
+        .get(STATE_VERSION_KEY)
+        .and_then(|version| version.trim().parse().ok())
+        .unwrap_or(0);
+
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(saved_version as usize) {
+        nih_log!("Migrating saved state from version {} to {}", version, version + 1);
+        migration(state);
+    }
+    state.fields.insert(STATE_VERSION_KEY.to_string(), STATE_VERSION.to_string());
+
+    // The editor size doesn't change the sound so it isn't versioned, it's fixed up by its shape
+    legacy_editor_size(state);
+}
+
+// Version 0 to 1: A Bass 2 became stereo, older sessions keep the mono version. Sessions this old
+// saved the algorithm's index rather than its id
+fn legacy_abass2(state: &mut PluginState) {
+    if let Some(ParamValue::I32(algorithm)) = state.params.get_mut("Algorithm") {
+        if *algorithm == AlgorithmType::ABass2 as i32 {
+            *algorithm = AlgorithmType::ABass2Legacy as i32;
+        }
+    }
+}
+
+// Version 1 to 2: the Gate Threshold knob was added on top of the airwindows leak that was there
+// before, older sessions get a gate that never closes so only the leak acts like it used to
+fn legacy_sub_gate(state: &mut PluginState) {
+    state.params.insert("Gate Threshold".to_string(), ParamValue::F32(util::MINUS_INFINITY_DB));
+}
+
+// Sessions saved with the shorter editor would open squashed and scaled down, keep the zoom the
+// width was saved at and grow the height to match the current layout
+fn legacy_editor_size(state: &mut PluginState) {
+    let Some(saved) = state.fields.get(EDITOR_STATE_KEY) else {
+        return;
+    };
+    let editor_state = EguiState::from_size(WIDTH, HEIGHT);
+    if editor_state.deserialize_field(saved).is_err() {
+        return;
+    }
+
+    // Allow for the size having been rounded at the older zooms
+    let (width, saved_height) = editor_state.size();
+    if (saved_height * WIDTH).abs_diff(width * LEGACY_HEIGHT) > WIDTH {
+        return;
+    }
+
+    let height = (width as f32 * HEIGHT as f32 / WIDTH as f32).round() as u32;
+    if let Ok(resized) = EguiState::from_size(width, height).serialize_field() {
+        nih_log!("Resizing the saved editor from {}x{} to {}x{}", width, saved_height, width, height);
+        state.fields.insert(EDITOR_STATE_KEY.to_string(), resized);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn saved_state(version: Option<u32>, algorithm: AlgorithmType) -> PluginState {
+        let mut fields: BTreeMap<String, String> = BTreeMap::new();
+        if let Some(version) = version {
+            fields.insert(STATE_VERSION_KEY.to_string(), version.to_string());
+        }
+        let mut params: BTreeMap<String, ParamValue> = BTreeMap::new();
+        params.insert("Algorithm".to_string(), ParamValue::I32(algorithm as i32));
+        PluginState { version: String::from("2.2.2"), params, fields }
+    }
+
+    fn algorithm(state: &PluginState) -> i32 {
+        match state.params.get("Algorithm") {
+            Some(ParamValue::I32(algorithm)) => *algorithm,
+            other => panic!("Algorithm saved as {other:?}"),
+        }
+    }
+
+    fn assert_gate_never_closes(state: &PluginState) {
+        assert!(matches!(state.params.get("Gate Threshold"), Some(ParamValue::F32(threshold)) if *threshold == util::MINUS_INFINITY_DB));
+    }
+
+    fn assert_current_version(state: &PluginState) {
+        assert_eq!(state.fields.get(STATE_VERSION_KEY).map(String::as_str), Some("2"));
+    }
+
+    #[test]
+    fn version_0_gets_legacy_abass2_and_an_open_gate() {
+        let mut state = saved_state(None, AlgorithmType::ABass2);
+        migrate(&mut state);
+        assert_eq!(algorithm(&state), AlgorithmType::ABass2Legacy as i32);
+        assert_gate_never_closes(&state);
+        assert_current_version(&state);
+    }
+
+    #[test]
+    fn version_1_keeps_abass2_and_gets_an_open_gate() {
+        let mut state = saved_state(Some(1), AlgorithmType::ABass2);
+        migrate(&mut state);
+        assert_eq!(algorithm(&state), AlgorithmType::ABass2 as i32);
+        assert_gate_never_closes(&state);
+        assert_current_version(&state);
+    }
+
+    #[test]
+    fn current_version_is_left_alone() {
+        let mut state = saved_state(Some(STATE_VERSION), AlgorithmType::ABass2);
+        migrate(&mut state);
+        assert_eq!(algorithm(&state), AlgorithmType::ABass2 as i32);
+        assert!(!state.params.contains_key("Gate Threshold"));
+        assert_current_version(&state);
+    }
+
+    #[test]
+    fn old_editor_size_keeps_its_zoom() {
+        let mut state = saved_state(None, AlgorithmType::ABass3);
+        let saved = EguiState::from_size(WIDTH * 3 / 2, LEGACY_HEIGHT * 3 / 2).serialize_field().unwrap();
+        state.fields.insert(EDITOR_STATE_KEY.to_string(), saved);
+        migrate(&mut state);
+
+        let editor_state = EguiState::from_size(WIDTH, HEIGHT);
+        editor_state.deserialize_field(&state.fields[EDITOR_STATE_KEY]).unwrap();
+        assert_eq!(editor_state.size(), (WIDTH * 3 / 2, HEIGHT * 3 / 2));
+    }
+}
@@ -0,0 +1,120 @@
+// Phase rotation and auto alignment for the generated sub using an allpass tuned to the sub's pitch
+// Ardura
+
+use crate::BiquadFilters::{Biquad, FilterType, BUTTERWORTH_Q};
+use std::f32::consts::PI;
+
+// Lower Q spreads the allpass phase change wider around the sub so pitch wobble matters less
+const ALLPASS_Q: f32 = 0.5;
+
+// The dry low band the sub is compared against
+const REFERENCE_LOWPASS_HZ: f32 = 300.0;
+
+// How quickly the auto align correlation settles, per sample
+const CORRELATION_SMOOTHING: f32 = 0.0002;
+
+// The allpass is only retuned once the center it wants has moved this far, as a share of it
+const RETUNE_TOLERANCE: f32 = 0.001;
+
+// Fade between the plain and aligned sub when the aligner turns on or off
+const FADE_MS: f32 = 10.0;
+
+#[derive(Clone, Copy)]
+pub struct SubAligner {
+    allpass: Biquad,
+    reference_lowpass: Biquad,
+    prev_sub: f32,
+    // Running correlation of the reference with the sub and with the sub shifted 90 degrees
+    in_phase: f32,
+    quadrature: f32,
+    // What the filters are tuned to, 0 until they're first set
+    tuned_freq: f32,
+    tuned_rate: f32,
+    // How much of the aligned sub is heard
+    mix: f32,
+}
+
+impl SubAligner {
+    pub fn new() -> Self {
+        Self {
+            allpass: Biquad::new(FilterType::AllPass),
+            reference_lowpass: Biquad::new(FilterType::LowPass),
+            prev_sub: 0.0,
+            in_phase: 0.0,
+            quadrature: 0.0,
+            tuned_freq: 0.0,
+            tuned_rate: 0.0,
+            mix: 0.0,
+        }
+    }
+
+    // Clears the filters for a new note. The measured alignment is kept, the next note on the same
+    // track lines up the same way
+    pub fn reset(&mut self) {
+        self.allpass.reset();
+        self.reference_lowpass.reset();
+        self.prev_sub = 0.0;
+    }
+
+    // `dry` is the summed input, `divider_sign` the flip-flop the sub follows and `sub_freq` the
+    // sub's fundamental in cycles per sample. `phase` is the extra lag in degrees. With no lag and
+    // auto align off the sub fades back to untouched and the aligner stops working
+    pub fn process(&mut self, sub: f32, dry: f32, divider_sign: f32, sub_freq: f32, sample_rate: f32, phase: f32, auto_align: bool) -> f32 {
+        let enabled: bool = phase > 0.0 || auto_align;
+        if enabled && self.mix == 0.0 {
+            // Coming back on, start from silence instead of whatever was left from last time
+            self.reset();
+            self.prev_sub = sub;
+            self.in_phase = 0.0;
+            self.quadrature = 0.0;
+        }
+        let fade_step: f32 = 1.0 / (FADE_MS * 0.001 * sample_rate);
+        self.mix = if enabled { (self.mix + fade_step).min(1.0) } else { (self.mix - fade_step).max(0.0) };
+        if self.mix == 0.0 {
+            return sub;
+        }
+
+        let w = 2.0 * PI * sub_freq.clamp(0.00001, 0.25);
+
+        // The dry low band flipped by the divider has the sub's pitch with the dry signal's timing
+        if sample_rate != self.tuned_rate {
+            self.reference_lowpass.set(sample_rate, REFERENCE_LOWPASS_HZ, BUTTERWORTH_Q, 0.0);
+        }
+        let reference = self.reference_lowpass.process(dry).abs() * divider_sign;
+
+        // A sine shifted 90 degrees is its slope over w
+        let sub_quadrature = -(sub - self.prev_sub) / w;
+        self.prev_sub = sub;
+        self.in_phase += (reference * sub - self.in_phase) * CORRELATION_SMOOTHING;
+        self.quadrature += (reference * sub_quadrature - self.quadrature) * CORRELATION_SMOOTHING;
+
+        let mut lag = phase;
+        if auto_align {
+            // Delay the rest of the way round to the next cycle
+            let measured_lag = (-self.quadrature).atan2(self.in_phase).to_degrees();
+            lag += 360.0 - measured_lag;
+        }
+        let lag = lag.rem_euclid(360.0);
+
+        // Tune the allpass so its phase at the sub's pitch is the lag we want
+        let ratio = allpass_ratio(lag);
+        let center_freq = if ratio > 0.0 { sub_freq * sample_rate / ratio } else { sample_rate };
+        if sample_rate != self.tuned_rate || (center_freq - self.tuned_freq).abs() > self.tuned_freq * RETUNE_TOLERANCE {
+            self.allpass.set(sample_rate, center_freq, ALLPASS_Q, 0.0);
+            self.tuned_freq = center_freq;
+            self.tuned_rate = sample_rate;
+        }
+        let aligned = self.allpass.process(sub);
+        sub + (aligned - sub) * self.mix
+    }
+}
+
+// Frequency over allpass center for a given lag. The allpass phase is -2 * atan2(r / Q, 1 - r^2)
+fn allpass_ratio(lag_degrees: f32) -> f32 {
+    let (sin, cos) = (lag_degrees * 0.5).to_radians().sin_cos();
+    if sin <= 0.0 {
+        return 0.0;
+    }
+    let b = cos / ALLPASS_Q;
+    (-b + (b * b + 4.0 * sin * sin).sqrt()) / (2.0 * sin)
+}
@@ -0,0 +1,70 @@
+// Ducks the generated sub on the host's beat grid or from the sidechain so it stays out of the kick's way
+// Ardura
+
+use nih_plug::prelude::*;
+
+// The sidechain has to fall this far under the threshold before it can trigger again
+const REARM_DB: f32 = 3.0;
+
+#[derive(Enum, PartialEq, Eq, Debug, Copy, Clone)]
+pub enum DuckTrigger {
+    #[name = "Off"]
+    Off,
+    #[name = "Beat Grid"]
+    BeatGrid,
+    #[name = "Sidechain"]
+    Sidechain,
+}
+
+// Duck settings for a block, lengths are in quarter notes
+pub struct DuckSettings {
+    pub trigger: DuckTrigger,
+    pub every: f64,
+    pub length: f64,
+    pub depth: f32,
+    pub shape: f32,
+    pub threshold_db: f32,
+}
+
+#[derive(Clone, Copy)]
+pub struct SubDucker {
+    // Time since the sidechain last triggered
+    elapsed_beats: Option<f64>,
+    armed: bool,
+}
+
+impl SubDucker {
+    pub fn new() -> Self {
+        Self { elapsed_beats: None, armed: true }
+    }
+
+    // Gain for the sub at one sample. `beats` is the host position and `sidechain` the sidechain peak
+    pub fn next(&mut self, settings: &DuckSettings, beats: Option<f64>, beats_per_sample: f64, sidechain: f32) -> f32 {
+        let elapsed: Option<f64> = match settings.trigger {
+            DuckTrigger::Off => None,
+            // The grid only exists while the host is playing
+            DuckTrigger::BeatGrid => beats.map(|beats| beats.rem_euclid(settings.every)),
+            DuckTrigger::Sidechain => {
+                let sidechain_db: f32 = util::gain_to_db_fast(sidechain);
+                if self.armed && sidechain_db >= settings.threshold_db {
+                    self.armed = false;
+                    self.elapsed_beats = Some(0.0);
+                } else if sidechain_db < settings.threshold_db - REARM_DB {
+                    self.armed = true;
+                }
+                let elapsed: Option<f64> = self.elapsed_beats;
+                self.elapsed_beats = elapsed.map(|elapsed| elapsed + beats_per_sample).filter(|elapsed| *elapsed < settings.length);
+                elapsed
+            }
+        };
+
+        match elapsed {
+            Some(elapsed) if elapsed < settings.length => {
+                // Shape bends the recovery from a straight line toward snapping back early
+                let remaining: f32 = 1.0 - (elapsed / settings.length) as f32;
+                1.0 - settings.depth * remaining.powf(1.0 + settings.shape * 4.0)
+            }
+            _ => 1.0,
+        }
+    }
+}
@@ -0,0 +1,126 @@
+// Gate on the sub generator's input so hum and room tone between notes don't flip the dividers
+// Ardura
+
+use nih_plug::prelude::*;
+
+// The detector falls this quickly so it doesn't close on every zero crossing, hold covers the rest
+const DETECTOR_RELEASE_MS: f32 = 10.0;
+
+// Opening ramps in over this so the sub doesn't click back in, the lookahead can cover it
+const ATTACK_MS: f32 = 2.0;
+
+// Closer than this to silent or to full the gain snaps there, so the release lands on zero instead
+// of running down for seconds
+const GAIN_FLOOR: f32 = 0.00001;
+
+#[derive(Clone, Copy)]
+pub struct SubGate {
+    threshold_db: f32,
+    close_db: f32,
+    hold_samples: u32,
+    detector_release: f32,
+    attack: f32,
+    release: f32,
+
+    detector: f32,
+    open: bool,
+    onset: bool,
+    hold_left: u32,
+    gain: f32,
+}
+
+impl SubGate {
+    // Starts always open until `set` is called
+    pub fn new() -> Self {
+        Self {
+            threshold_db: util::MINUS_INFINITY_DB,
+            close_db: util::MINUS_INFINITY_DB,
+            hold_samples: 0,
+            detector_release: 0.0,
+            attack: 0.0,
+            release: 0.0,
+            detector: 0.0,
+            open: true,
+            onset: false,
+            hold_left: 0,
+            gain: 1.0,
+        }
+    }
+
+    pub fn set(&mut self, threshold_db: f32, hysteresis_db: f32, hold_ms: f32, release_ms: f32, sample_rate: f32) {
+        self.threshold_db = threshold_db;
+        self.close_db = threshold_db - hysteresis_db;
+        self.hold_samples = (hold_ms * 0.001 * sample_rate) as u32;
+        self.detector_release = (-1.0 / (DETECTOR_RELEASE_MS * 0.001 * sample_rate)).exp();
+        self.attack = (-1.0 / (ATTACK_MS * 0.001 * sample_rate)).exp();
+        self.release = (-1.0 / (release_ms * 0.001 * sample_rate)).exp();
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    // True for the sample the gate opened on after fading the sub all the way out
+    pub fn is_onset(&self) -> bool {
+        self.onset
+    }
+
+    // Gain for the sub at one sample of the summed input
+    pub fn process(&mut self, input: f32) -> f32 {
+        self.detector = input.abs().max(self.detector * self.detector_release);
+        let was_open: bool = self.open;
+
+        // All the way down never closes, whatever the detector rounds to in silence
+        if self.threshold_db <= util::MINUS_INFINITY_DB {
+            self.open = true;
+        } else {
+            let level_db: f32 = util::gain_to_db_fast(self.detector);
+
+            // Opens over the threshold and only closes once it's been under threshold - hysteresis for the hold time
+            if level_db >= self.threshold_db {
+                self.open = true;
+            }
+            if level_db >= self.close_db {
+                self.hold_left = self.hold_samples;
+            } else if self.hold_left > 0 {
+                self.hold_left -= 1;
+            } else {
+                self.open = false;
+            }
+        }
+
+        // Opening from silence is a new note rather than the last one coming back
+        self.onset = self.open && !was_open && self.gain == 0.0;
+        self.gain = if self.open { 1.0 - (1.0 - self.gain) * self.attack } else { self.gain * self.release };
+        if self.gain < GAIN_FLOOR { self.gain = 0.0; }
+        if self.gain > 1.0 - GAIN_FLOOR { self.gain = 1.0; }
+        self.gain
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44100.0;
+
+    #[test]
+    fn reopening_ramps_the_gain_back_up() {
+        let mut gate = SubGate::new();
+        gate.set(-40.0, 0.0, 0.0, 1.0, SAMPLE_RATE);
+        for _ in 0..4410 {
+            gate.process(0.0);
+        }
+        assert_eq!(gate.process(0.0), 0.0);
+
+        // No step bigger than the first one of the attack on the way back to full
+        let mut previous: f32 = 0.0;
+        for _ in 0..(ATTACK_MS * 0.02 * SAMPLE_RATE) as usize {
+            let gain: f32 = gate.process(0.5);
+            assert!(gain - previous <= 1.0 - gate.attack + f32::EPSILON, "gain jumped from {previous} to {gain}");
+            assert!(gain >= previous);
+            previous = gain;
+        }
+        assert_eq!(previous, 1.0);
+    }
+}
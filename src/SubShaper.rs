@@ -0,0 +1,103 @@
+// Morphs the generated sub between sine, triangle, the original filtered square and saturated
+// Ardura
+
+use crate::tape_saturation;
+use std::f32::consts::PI;
+
+// In order along the Sub Shape knob
+pub const SHAPE_NAMES: [&str; 4] = ["Sine", "Triangle", "Square", "Saturated"];
+
+// Where the knob sits for the original Subhoofer sub
+pub const ORIGINAL_SHAPE: f32 = 2.0;
+
+// Drive into tape_saturation for the saturated shape
+const SATURATION_DRIVE: f32 = 4.0;
+
+// Corner of the triangle's leaky integrator, well under any sub so only DC drains away
+const TRIANGLE_LEAK_HZ: f32 = 3.5;
+
+// How quickly the saturated shape's level follower falls
+const ENVELOPE_RELEASE_MS: f32 = 45.0;
+
+#[derive(Clone, Copy)]
+pub struct SubShaper {
+    tracking: [f32; 3],
+    triangle: f32,
+    envelope: f32,
+    triangle_leak: f32,
+    envelope_release: f32,
+}
+
+impl SubShaper {
+    // Starts at 44.1 kHz until `set_sample_rate` is called
+    pub fn new() -> Self {
+        let mut shaper = Self {
+            tracking: [0.0; 3],
+            triangle: 0.0,
+            envelope: 0.0,
+            triangle_leak: 0.0,
+            envelope_release: 0.0,
+        };
+        shaper.set_sample_rate(44100.0);
+        shaper
+    }
+
+    // Back to silence for a new note
+    pub fn reset(&mut self) {
+        self.tracking = [0.0; 3];
+        self.triangle = 0.0;
+        self.envelope = 0.0;
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.triangle_leak = (-2.0 * PI * TRIANGLE_LEAK_HZ / sample_rate).exp();
+        self.envelope_release = (-1.0 / (ENVELOPE_RELEASE_MS * 0.001 * sample_rate)).exp();
+    }
+
+    // `square` is the filtered square sub and `sub_freq` its fundamental in cycles per sample.
+    // Every shape runs all the time so moving the knob never starts a filter from silence
+    pub fn process(&mut self, square: f32, sub_freq: f32, shape: f32) -> f32 {
+        let sub_freq = sub_freq.clamp(0.00001, 0.25);
+
+        // Sine - three one pole low passes tracking the fundamental, made back up to the square's level
+        let coefficient = 1.0 - (-2.0 * PI * sub_freq).exp();
+        let mut sine = square;
+        for stage in self.tracking.iter_mut() {
+            *stage += (sine - *stage) * coefficient;
+            sine = *stage;
+        }
+        let pole = 1.0 - coefficient;
+        let stage_gain = coefficient / (1.0 - 2.0 * pole * (2.0 * PI * sub_freq).cos() + pole * pole).sqrt();
+        // The fundamental of a square is 4/pi of its level
+        sine *= PI / 4.0 / stage_gain.powi(3);
+
+        // Triangle - leaky integral of the square, scaled so the peaks match
+        self.triangle = self.triangle * self.triangle_leak + square * 4.0 * sub_freq;
+
+        // Saturated - the square through the sub drive's tape saturation at a steady level
+        self.envelope = square.abs().max(self.envelope * self.envelope_release).max(1.0e-9);
+        let normalized = square / self.envelope;
+        let saturated = (normalized + tape_saturation(normalized, SATURATION_DRIVE))
+            / (1.0 + tape_saturation(1.0, SATURATION_DRIVE))
+            * self.envelope;
+
+        let shapes = [sine, self.triangle, square, saturated];
+        let shape = shape.clamp(0.0, (SHAPE_NAMES.len() - 1) as f32);
+        let idx = (shape.floor() as usize).min(SHAPE_NAMES.len() - 2);
+        let frac = shape - idx as f32;
+        shapes[idx] + (shapes[idx + 1] - shapes[idx]) * frac
+    }
+}
+
+// Knob text - the shape name, or the two shapes being morphed between
+pub fn shape_name(value: f32) -> String {
+    let idx = (value.floor().max(0.0) as usize).min(SHAPE_NAMES.len() - 2);
+    let frac = value - idx as f32;
+    if frac < 0.01 {
+        SHAPE_NAMES[idx].to_string()
+    } else if frac > 0.99 {
+        SHAPE_NAMES[idx + 1].to_string()
+    } else {
+        format!("{} > {} {:.0}%", SHAPE_NAMES[idx], SHAPE_NAMES[idx + 1], frac * 100.0)
+    }
+}
@@ -2,10 +2,21 @@
 mod CustomWidgets;
 use atomic_float::AtomicF32;
 use nih_plug::{prelude::*};
-use nih_plug_egui::{create_egui_editor, egui::{self, Color32, FontId, Pos2, Rect, RichText, Rounding}, widgets, EguiState};
-use CustomWidgets::{db_meter, ui_knob};
-use std::{f32::consts::PI, ops::RangeInclusive, sync::Arc};
-mod SweetenX;
+use nih_plug_egui::{create_egui_editor, egui::{self, FontId, RichText, Rounding}, resizable_window::ResizableWindow, widgets, EguiState};
+use Modulation::{ModSource, ModTarget, NUM_LFOS, NUM_SLOTS, NUM_TARGETS};
+use CustomWidgets::{db_meter, harmonic_drawbars, signal_plot::{self, ScopeBuffer}, theme::{self, Theme}, ui_knob};
+use std::{f32::consts::PI, sync::{atomic::AtomicBool, Arc, RwLock}};
+mod BiquadFilters;
+mod ChebyshevStack;
+mod LinearRamp;
+mod Lookahead;
+mod Modulation;
+mod StateMigration;
+mod SubAligner;
+mod SubDucker;
+mod SubGate;
+mod SubShaper;
+pub mod SweetenX;
 
 /***************************************************************************
  * Subhoofer v2.2.2 by Ardura
@@ -13,35 +24,124 @@ mod SweetenX;
  * Build with: cargo xtask bundle Subhoofer --profile <release or profiling>
  * *************************************************************************/
 
+ #[derive(Enum, PartialEq, Eq, Debug, Copy, Clone)]
+ pub enum SubDestination{
+    #[name = "Mains"]
+    Mains,
+    #[name = "LFE"]
+    Lfe,
+    #[name = "Both"]
+    Both,
+ }
+
+ #[derive(Enum, PartialEq, Eq, Debug, Copy, Clone)]
+ pub enum StereoMode{
+    #[name = "L/R"]
+    LeftRight,
+    #[name = "Mid"]
+    Mid,
+    #[name = "Side"]
+    Side,
+    #[name = "Mid+Side"]
+    MidSide,
+ }
+
+ #[derive(Enum, PartialEq, Eq, Debug, Copy, Clone)]
+ pub enum DynamicsMode{
+    #[name = "Off"]
+    Off,
+    #[name = "Boost Quiet"]
+    BoostQuiet,
+    #[name = "Boost Loud"]
+    BoostLoud,
+ }
+
  #[derive(Enum, PartialEq, Eq, Debug, Copy, Clone)]
  pub enum AlgorithmType{
+    #[id = "abass3"]
     #[name = "A Bass 3"]
     ABass3,
+    #[id = "abass2"]
     #[name = "A Bass 2"]
     ABass2,
+    #[id = "harmonic-stack"]
     #[name = "8 Harmonic Stack"]
     BBass,
+    #[id = "duro-console"]
     #[name = "Duro Console"]
     CBass,
+    #[id = "tanh-transfer"]
     #[name = "TanH Transfer"]
     TanH,
+    #[id = "custom"]
     #[name = "Custom"]
     CustomSliders,
+    #[id = "chebyshev-drawbars"]
+    #[name = "Chebyshev Drawbars"]
+    ChebyshevBars,
+    // Sessions save the ids, kept last so sessions from before the ids keep their indices
+    #[id = "abass2-legacy"]
+    #[name = "A Bass 2 (Legacy)"]
+    ABass2Legacy,
  }
 
- // GUI Colors
-const TEAL: Color32 = Color32::from_rgb(13,62,102);
-const NAVY_BLUE: Color32 = Color32::from_rgb(55,50,48);
-const BEIGE: Color32 = Color32::from_rgb(239,141,11);
-const LIGHT_GREY: Color32 = Color32::from_rgb(204,205,196);
-
-// Plugin sizing
+// Plugin sizing - the layout is designed at this size and scaled from there
 const WIDTH: u32 = 360;
-const HEIGHT: u32 = 528;
+const HEIGHT: u32 = 640;
+
+// Zoom presets offered in the editor
+const ZOOM_PRESETS: [f32; 6] = [0.75, 1.0, 1.25, 1.5, 1.75, 2.0];
 
 /// The time it takes for the peak meter to decay by 12 dB after switching to complete silence.
 const PEAK_METER_DECAY_MS: f64 = 100.0;
 
+/// Samples kept for the visualizer scope, about 20ms at 48kHz
+const SCOPE_SIZE: usize = 1024;
+
+/// Most Dynamic Harmonics can raise the harmonics by
+const MAX_DYNAMIC_HARMONICS_DB: f32 = 24.0;
+
+/// Points plotted on the transfer curve
+const CURVE_POINTS: usize = 128;
+
+/// Most samples processed at once, smoothed values are filled this many at a time
+pub const BLOCK_SIZE: usize = 64;
+
+/// 7.1 is the widest layout
+const MAX_CHANNELS: usize = 8;
+
+// Harmonic EQ range, the ends of it mean the filter is out of the way
+const HARMONIC_EQ_MIN_HZ: f32 = 20.0;
+const HARMONIC_EQ_MAX_HZ: f32 = 20000.0;
+
+// Where the harmonic tilt pivots
+const TILT_PIVOT_HZ: f32 = 500.0;
+
+// Sub input range knobs. At these ends that side of the range is off, nothing fades past it
+const SUB_RANGE_MIN_HZ: f32 = 10.0;
+const SUB_RANGE_MAX_HZ: f32 = 1000.0;
+
+// Outside the sub input range the sub fades out over this far and this long
+const SUB_RANGE_EDGE_OCTAVES: f32 = 0.333;
+const SUB_RANGE_FADE_MS: f32 = 20.0;
+
+// Channel pairs the harmonics run on, and where the LFE sits in surround layouts
+const NUM_PAIRS: usize = 4;
+const FRONT_PAIR: usize = 0;
+const CENTER_PAIR: usize = 1;
+const LFE_CHANNEL: usize = 3;
+
+// Which page of the editor is showing
+#[derive(Clone, Copy, PartialEq)]
+enum EditorPage {
+    Main,
+    Tone,
+    Sub,
+    Modulation,
+    Visualizer,
+    Settings,
+}
+
 pub struct Subhoofer {
     params: Arc<SubhooferParams>,
 
@@ -49,10 +149,6 @@ pub struct Subhoofer {
     out_meter_decay_weight: f32,
 
     // "header" variables from C++ class
-    prev_processed_in_r: f32,
-    prev_processed_out_r: f32,
-    prev_processed_in_l: f32,
-    prev_processed_out_l: f32,
     lp: f32,
     iir_sub_bump_a: f32,
     iir_sub_bump_b: f32,
@@ -64,6 +160,7 @@ pub struct Subhoofer {
     iir_drive_sample_e: f32,
     iir_drive_sample_f: f32,
     osc_gate: f32,
+    sub_gate: SubGate::SubGate,
     iir_sample_a: f32,
     iir_sample_b: f32,
     iir_sample_c: f32,
@@ -94,19 +191,154 @@ pub struct Subhoofer {
 
     // Logic control variables
     sub_octave: bool,
+    sub_octave_2: bool,
+    sub_fifth: bool,
+    fifth_half_cycles: u32,
     was_negative: bool,
+    samples_since_crossing: u32,
+    input_period: u32,
+    // Whether the count since the last crossing is a real period, it isn't across a rest
+    period_counting: bool,
+    sub_range_gain: f32,
+    sub_shaper: SubShaper::SubShaper,
+    sub_aligner: SubAligner::SubAligner,
     bass_flip_counter: i32,
 
+    // Mod matrix sources and the host tempo and position the LFOs sync to
+    lfos: [Modulation::Lfo; NUM_LFOS],
+    mod_envelope: Modulation::EnvelopeFollower,
+    host_clock: Modulation::HostClock,
+
+    // Sub ducking and the sidechain peak for each sample of the block, filled by process()
+    sub_ducker: SubDucker::SubDucker,
+    sidechain: [f32; BLOCK_SIZE],
+
     // The current data for the different meters
     out_meter: Arc<AtomicF32>,
     in_meter: Arc<AtomicF32>,
 
-    // Buffer for SweetenX
-    buffer: [f32; 16],
+    // Whether the sub gate is open for the editor's indicator
+    gate_open: Arc<AtomicBool>,
+
+    // Dynamic Harmonics envelope and the gain it's putting on the harmonics for the gauge
+    dynamics_envelope: Modulation::EnvelopeFollower,
+    dynamics_gain: Arc<AtomicF32>,
+
+    // Knob smoothing that follows the Smoothing Time setting
+    ramps: ParamRamps,
+
+    // Algorithm crossfade state - the algorithm we're on and the one fading out
+    current_algorithm: AlgorithmType,
+    fade_from_algorithm: AlgorithmType,
+    fade_length: u32,
+    fade_samples_left: u32,
+
+    // Recent input and output for the visualizer scope
+    scope_in: Arc<ScopeBuffer>,
+    scope_out: Arc<ScopeBuffer>,
+
+    // Harmonic state for the front, center, side and rear pairs of channels
+    pairs: [ChannelPair; NUM_PAIRS],
+
+    // Sub and harmonic layers of the front pair for the aux outputs, filled by each block
+    sub_layer: [[f32; BLOCK_SIZE]; 2],
+    harmonic_layer: [[f32; BLOCK_SIZE]; 2],
+
+    // Whether the host gave us aux output buffers for this process call
+    aux_outputs: bool,
+
+    // DC blocker for the sub on the LFE channel
+    prev_lfe_in: f32,
+    prev_lfe_out: f32,
+
+    // Delay on the main channels ahead of the sub generator
+    lookahead: Lookahead::Lookahead,
+}
+
+// Everything the harmonic path keeps between samples for one pair of channels
+#[derive(Clone, Copy)]
+struct ChannelPair {
+    prev_processed_in_r: f32,
+    prev_processed_out_r: f32,
+    prev_processed_in_l: f32,
+    prev_processed_out_l: f32,
+
+    // DC blocker for the harmonic layer when it's split out for the aux outputs
+    prev_harmonic_in_r: f32,
+    prev_harmonic_out_r: f32,
+    prev_harmonic_in_l: f32,
+    prev_harmonic_out_l: f32,
+
+    // Buffer for SweetenX, and a separate one for the algorithm fading out
+    buffer: SweetenX::Buffer,
+    fade_buffer: SweetenX::Buffer,
+
+    // Small speaker crossover - the low band feeds the harmonics and the high pass removes it after
+    speaker_split_l: [BiquadFilters::Biquad; 2],
+    speaker_split_r: [BiquadFilters::Biquad; 2],
+    speaker_hp_l: [BiquadFilters::Biquad; 2],
+    speaker_hp_r: [BiquadFilters::Biquad; 2],
+
+    // Harmonic EQ - high pass, low pass, then the low and high shelves of the tilt
+    harmonic_eq_l: [BiquadFilters::Biquad; 4],
+    harmonic_eq_r: [BiquadFilters::Biquad; 4],
+}
+
+impl ChannelPair {
+    fn new() -> Self {
+        Self {
+            prev_processed_in_r: 0.0,
+            prev_processed_out_r: 0.0,
+            prev_processed_in_l: 0.0,
+            prev_processed_out_l: 0.0,
+            prev_harmonic_in_r: 0.0,
+            prev_harmonic_out_r: 0.0,
+            prev_harmonic_in_l: 0.0,
+            prev_harmonic_out_l: 0.0,
+            buffer: [[0.0; 2]; 8],
+            fade_buffer: [[0.0; 2]; 8],
+            speaker_split_l: [BiquadFilters::Biquad::new(BiquadFilters::FilterType::LowPass); 2],
+            speaker_split_r: [BiquadFilters::Biquad::new(BiquadFilters::FilterType::LowPass); 2],
+            speaker_hp_l: [BiquadFilters::Biquad::new(BiquadFilters::FilterType::HighPass); 2],
+            speaker_hp_r: [BiquadFilters::Biquad::new(BiquadFilters::FilterType::HighPass); 2],
+            harmonic_eq_l: Self::new_harmonic_eq(),
+            harmonic_eq_r: Self::new_harmonic_eq(),
+        }
+    }
+
+    fn new_harmonic_eq() -> [BiquadFilters::Biquad; 4] {
+        [
+            BiquadFilters::Biquad::new(BiquadFilters::FilterType::HighPass),
+            BiquadFilters::Biquad::new(BiquadFilters::FilterType::LowPass),
+            BiquadFilters::Biquad::new(BiquadFilters::FilterType::LowShelf),
+            BiquadFilters::Biquad::new(BiquadFilters::FilterType::HighShelf),
+        ]
+    }
+
+    // The tilt cuts one side of the pivot as much as it boosts the other
+    fn set_harmonic_eq(&mut self, sample_rate: f32, high_pass: f32, low_pass: f32, tilt: f32) {
+        for eq in [&mut self.harmonic_eq_l, &mut self.harmonic_eq_r] {
+            eq[0].set(sample_rate, high_pass, BiquadFilters::BUTTERWORTH_Q, 0.0);
+            eq[1].set(sample_rate, low_pass.min(sample_rate * 0.45), BiquadFilters::BUTTERWORTH_Q, 0.0);
+            eq[2].set(sample_rate, TILT_PIVOT_HZ, BiquadFilters::BUTTERWORTH_Q, -tilt * 0.5);
+            eq[3].set(sample_rate, TILT_PIVOT_HZ, BiquadFilters::BUTTERWORTH_Q, tilt * 0.5);
+        }
+    }
+}
+
+// Channels of each pair in the buffer. Surround layouts are in the usual L R C LFE Ls Rs (Lrs Rrs)
+// order, and a single channel (mono or center) runs through both lanes
+fn pair_channels(num_channels: usize) -> [Option<(usize, usize)>; NUM_PAIRS] {
+    match num_channels {
+        1 => [Some((0, 0)), None, None, None],
+        6 => [Some((0, 1)), Some((2, 2)), Some((4, 5)), None],
+        8 => [Some((0, 1)), Some((2, 2)), Some((4, 5)), Some((6, 7))],
+        _ => [Some((0, 1)), None, None, None],
+    }
 }
 
 // Modified function from Duro Console for different behavior - hoof hardness
-fn chebyshev_tape(sample: f32, drive: f32) -> f32 {
+pub fn chebyshev_tape(sample: f32, drive: f32) -> f32 {
     let dry = 1.0 - drive;
     let peak = f32::max(sample.abs(), 1.0);
     let x = sample / peak;
@@ -123,7 +355,7 @@ fn chebyshev_tape(sample: f32, drive: f32) -> f32 {
 }
 
 // Modified tape saturation using transfer function from Duro Console
-fn tape_saturation(input_signal: f32, drive: f32) -> f32 {
+pub fn tape_saturation(input_signal: f32, drive: f32) -> f32 {
     let idrive = drive;
     // Define the transfer curve for the tape saturation effect
     let transfer = |x: f32| -> f32 {
@@ -139,7 +371,7 @@ fn tape_saturation(input_signal: f32, drive: f32) -> f32 {
 
 /* One of the other algorithms I was messing around with - not exactly the
     sound I was going for but unique enough to include - Ardura */
-fn b_bass_saturation(signal: f32, mut harmonic_strength: f32) -> f32 {
+pub fn b_bass_saturation(signal: f32, mut harmonic_strength: f32) -> f32 {
     let num_harmonics: usize = 8;
     let mut summed: f32 = 0.0;
 
@@ -165,7 +397,7 @@ fn b_bass_saturation(signal: f32, mut harmonic_strength: f32) -> f32 {
 
 
 // Modified "odd_saturation" from Duro Console
-fn c_bass_saturation(signal: f32, harmonic_strength: f32) -> f32 {
+pub fn c_bass_saturation(signal: f32, harmonic_strength: f32) -> f32 {
     let num_harmonics: usize = 7;
     let mut summed: f32 = 0.0;
     for j in 1..=num_harmonics {
@@ -178,7 +410,7 @@ fn c_bass_saturation(signal: f32, harmonic_strength: f32) -> f32 {
 }
 
 
-fn custom_sincos_saturation(signal: f32, harmonic_strength1: f32, harmonic_strength2: f32, harmonic_strength3: f32, harmonic_strength4: f32) -> f32 {
+pub fn custom_sincos_saturation(signal: f32, harmonic_strength1: f32, harmonic_strength2: f32, harmonic_strength3: f32, harmonic_strength4: f32) -> f32 {
     let mut summed: f32 = 0.0;
 
     let harmonic_component: f32 = harmonic_strength1 * (signal * 1.0).cos() - signal;
@@ -196,6 +428,353 @@ fn custom_sincos_saturation(signal: f32, harmonic_strength1: f32, harmonic_stren
     summed
 }
 
+// Everything the harmonic algorithms read besides the input
+#[derive(Clone, Copy)]
+struct HarmonicSettings {
+    harmonics: f32,
+    hoof_hardness: f32,
+    custom_harmonics1: f32,
+    custom_harmonics2: f32,
+    custom_harmonics3: f32,
+    custom_harmonics4: f32,
+    drawbar_gains: [f32; ChebyshevStack::NUM_ORDERS],
+    overall_scale: f32,
+}
+
+impl HarmonicSettings {
+    // Unsmoothed values for the editor
+    fn from_params(params: &SubhooferParams, overall_scale: f32) -> Self {
+        let mut drawbar_gains: [f32; ChebyshevStack::NUM_ORDERS] = [0.0; ChebyshevStack::NUM_ORDERS];
+        for (gain, drawbar) in drawbar_gains.iter_mut().zip(params.drawbars.iter()) {
+            *gain = if drawbar.invert.value() { -drawbar.gain.value() } else { drawbar.gain.value() };
+        }
+        Self {
+            harmonics: params.harmonics.value(),
+            hoof_hardness: params.hoof_hardness.value(),
+            custom_harmonics1: params.custom_harmonics1.value(),
+            custom_harmonics2: params.custom_harmonics2.value(),
+            custom_harmonics3: params.custom_harmonics3.value(),
+            custom_harmonics4: params.custom_harmonics4.value(),
+            drawbar_gains,
+            overall_scale,
+        }
+    }
+}
+
+// Everything the sub generator reads besides the input
+#[derive(Clone, Copy)]
+pub struct SubSettings {
+    pub hoof_hardness: f32,
+    pub sub_gain: f32,
+    pub sub_drive: f32,
+    // Levels of the -1 octave, -2 octave and fifth below dividers
+    pub divider_gains: [f32; 3],
+    pub sub_shape: f32,
+    // Extra lag in degrees
+    pub sub_phase: f32,
+    pub sub_invert: bool,
+    pub auto_align: bool,
+    // Input fundamentals the sub follows
+    pub min_freq: f32,
+    pub max_freq: f32,
+    pub overall_scale: f32,
+}
+
+// Harmonic input lanes for the stereo mode - L/R as is or mid and side
+fn encode_stereo(mode: StereoMode, in_l: f32, in_r: f32) -> (f32, f32) {
+    match mode {
+        StereoMode::LeftRight => (in_l, in_r),
+        _ => ((in_l + in_r) * 0.5, (in_l - in_r) * 0.5),
+    }
+}
+
+// Harmonics back to L/R from `encode_stereo` lanes with the mid and side depths
+fn decode_stereo(mode: StereoMode, lane_a: f32, lane_b: f32, mid_depth: f32, side_depth: f32) -> (f32, f32) {
+    let (mid_depth, side_depth) = match mode {
+        StereoMode::LeftRight => return (lane_a, lane_b),
+        StereoMode::Mid => (1.0, 0.0),
+        StereoMode::Side => (0.0, 1.0),
+        StereoMode::MidSide => (mid_depth, side_depth),
+    };
+    let mid: f32 = lane_a * mid_depth;
+    let side: f32 = lane_b * side_depth;
+    (mid + side, mid - side)
+}
+
+// Static input vs output of an algorithm for the editor's transfer curve. Each point gets a fresh
+// SweetenX buffer fed the same value until its averaging settles, and the sub is left out
+fn transfer_curve_point(h_algorithm: AlgorithmType, input: f32, settings: &HarmonicSettings) -> f32 {
+    let mut buffer: SweetenX::Buffer = [[0.0; 2]; 8];
+    let mut output: f32 = 0.0;
+    for _ in 0..8 {
+        (output, _) = apply_algorithm(h_algorithm, input, input, 0.0, 0.0, settings, &mut buffer);
+    }
+    input + output
+}
+
+// apply_algorithm over a block. The algorithm is picked once here and each arm runs its own loop
+// with it fixed, so the per sample match is inlined away. Only SweetenX works on both lanes at
+// once, the other algorithms are scalar per lane
+fn apply_algorithm_block(
+    h_algorithm: AlgorithmType,
+    harm_in: (&[f32; BLOCK_SIZE], &[f32; BLOCK_SIZE]),
+    sub_bump: &[f32; BLOCK_SIZE],
+    sub_gain: &[f32; BLOCK_SIZE],
+    settings: &[HarmonicSettings; BLOCK_SIZE],
+    block_len: usize,
+    buffer: &mut SweetenX::Buffer,
+) -> ([f32; BLOCK_SIZE], [f32; BLOCK_SIZE]) {
+    match h_algorithm {
+        AlgorithmType::ABass3 => algorithm_loop(AlgorithmType::ABass3, harm_in, sub_bump, sub_gain, settings, block_len, buffer),
+        AlgorithmType::ABass2 => algorithm_loop(AlgorithmType::ABass2, harm_in, sub_bump, sub_gain, settings, block_len, buffer),
+        AlgorithmType::BBass => algorithm_loop(AlgorithmType::BBass, harm_in, sub_bump, sub_gain, settings, block_len, buffer),
+        AlgorithmType::CBass => algorithm_loop(AlgorithmType::CBass, harm_in, sub_bump, sub_gain, settings, block_len, buffer),
+        AlgorithmType::TanH => algorithm_loop(AlgorithmType::TanH, harm_in, sub_bump, sub_gain, settings, block_len, buffer),
+        AlgorithmType::CustomSliders => algorithm_loop(AlgorithmType::CustomSliders, harm_in, sub_bump, sub_gain, settings, block_len, buffer),
+        AlgorithmType::ChebyshevBars => algorithm_loop(AlgorithmType::ChebyshevBars, harm_in, sub_bump, sub_gain, settings, block_len, buffer),
+        AlgorithmType::ABass2Legacy => algorithm_loop(AlgorithmType::ABass2Legacy, harm_in, sub_bump, sub_gain, settings, block_len, buffer),
+    }
+}
+
+#[inline(always)]
+fn algorithm_loop(
+    h_algorithm: AlgorithmType,
+    harm_in: (&[f32; BLOCK_SIZE], &[f32; BLOCK_SIZE]),
+    sub_bump: &[f32; BLOCK_SIZE],
+    sub_gain: &[f32; BLOCK_SIZE],
+    settings: &[HarmonicSettings; BLOCK_SIZE],
+    block_len: usize,
+    buffer: &mut SweetenX::Buffer,
+) -> ([f32; BLOCK_SIZE], [f32; BLOCK_SIZE]) {
+    let mut out_l: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+    let mut out_r: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+    for idx in 0..block_len {
+        (out_l[idx], out_r[idx]) = apply_algorithm(h_algorithm, harm_in.0[idx], harm_in.1[idx], sub_bump[idx], sub_gain[idx], &settings[idx], buffer);
+    }
+    (out_l, out_r)
+}
+
+// Harmonics + sub for the chosen algorithm followed by the hardness saturation.
+// Shared by process() and the transfer curve in the editor
+#[inline(always)]
+fn apply_algorithm(
+    h_algorithm: AlgorithmType,
+    harm_in_l: f32,
+    harm_in_r: f32,
+    sub_bump: f32,
+    sub_gain: f32,
+    settings: &HarmonicSettings,
+    buffer: &mut SweetenX::Buffer,
+) -> (f32, f32) {
+    let HarmonicSettings {
+        harmonics,
+        hoof_hardness,
+        custom_harmonics1,
+        custom_harmonics2,
+        custom_harmonics3,
+        custom_harmonics4,
+        ref drawbar_gains,
+        overall_scale,
+    } = *settings;
+    let mut processed_sample_l: f32;
+    let mut processed_sample_r: f32;
+
+    // Add: Original signal + Harmonics + Sub signal
+    match h_algorithm {
+        AlgorithmType::ABass3 => {
+            let harmonic2_l: f32;
+            let harmonic2_r: f32;
+            let harmonic3_l: f32;
+            let harmonic3_r: f32;
+            let harmonic4_l: f32;
+            let harmonic4_r: f32;
+            let harmonic5_l: f32;
+            let harmonic5_r: f32;
+            let harmonic6_l: f32;
+            let harmonic6_r: f32;
+            let harmonic7_l: f32;
+            let harmonic7_r: f32;
+            let harmonic8_l: f32;
+            let harmonic8_r: f32;
+            let harmonic9_l: f32;
+            let harmonic9_r: f32;
+            let harmonic10_l: f32;
+            let harmonic10_r: f32;
+            let harmonic11_l: f32;
+            let harmonic11_r: f32;
+            let harmonic12_l: f32;
+            let harmonic12_r: f32;
+            let harmonic13_l: f32;
+            let harmonic13_r: f32;
+            let harmonic14_l: f32;
+            let harmonic14_r: f32;
+
+            (harmonic2_l, harmonic2_r) = SweetenX::process(harm_in_l, harm_in_r, overall_scale, 26.470589, 2, buffer);
+            (harmonic3_l, harmonic3_r) = SweetenX::process(harm_in_l, harm_in_r, overall_scale, 8.941176, 3, buffer);
+            (harmonic4_l, harmonic4_r) = SweetenX::process(harm_in_l, harm_in_r, overall_scale, 0.1764706, 4, buffer);
+            (harmonic5_l, harmonic5_r) = SweetenX::process(harm_in_l, harm_in_r, overall_scale, 0.0, 5, buffer);
+            (harmonic6_l, harmonic6_r) = SweetenX::process(harm_in_l, harm_in_r, overall_scale, 0.0, 6, buffer);
+            (harmonic7_l, harmonic7_r) = SweetenX::process(harm_in_l, harm_in_r, overall_scale, 0.0, 7, buffer);
+            (harmonic8_l, harmonic8_r) = SweetenX::process(harm_in_l, harm_in_r, overall_scale, 0.0, 8, buffer);
+            (harmonic9_l, harmonic9_r) = SweetenX::process(harm_in_l, harm_in_r, overall_scale, 171.76471, 9, buffer);
+
+            let octave_l = harm_in_l * harm_in_l * harm_in_l * harm_in_l * harm_in_l * 0.5;
+            let octave_r = harm_in_r * harm_in_r * harm_in_r * harm_in_r * harm_in_r * 0.5;
+            // Start from 5th
+            (harmonic10_l, harmonic10_r) = SweetenX::process(octave_l, octave_r, overall_scale, 0.0, 2, buffer);
+            (harmonic11_l, harmonic11_r) = SweetenX::process(octave_l, octave_r, overall_scale, 4000.0, 3, buffer);
+            (harmonic12_l, harmonic12_r) = SweetenX::process(octave_l, octave_r, overall_scale, 11764706.0, 4, buffer);
+            (harmonic13_l, harmonic13_r) = SweetenX::process(octave_l, octave_r, overall_scale, 5294118000.0, 5, buffer);
+            (harmonic14_l, harmonic14_r) = SweetenX::process(octave_l, octave_r, overall_scale, 17647059000.0, 6, buffer);
+
+            processed_sample_l = harm_in_l;
+            processed_sample_r = harm_in_r;
+
+            // Sum all harmonics into the processed sample
+            //processed_sample_l += harmonic2_l + harmonic3_l + (harmonic7_l - harmonic5_l*2.0 - harmonic3_l*2.0 - harmonic2_l*2.0) + (harmonic9_l - harmonic2_l*2.0) + (sub_bump * sub_gain);
+            processed_sample_l += (harmonic2_l + harmonic3_l + harmonic4_l + harmonic5_l + 
+                harmonic6_l + harmonic7_l + harmonic8_l + harmonic9_l + 
+                harmonic10_l + harmonic11_l + harmonic12_l + harmonic13_l + 
+                harmonic14_l)*(harmonics * 1497.00599) + (sub_bump * sub_gain);
+            //processed_sample_r += harmonic2_r + harmonic3_r + (harmonic7_r - harmonic5_r*2.0 - harmonic3_r*2.0 - harmonic2_r*2.0) + (harmonic9_r - harmonic2_r*2.0) + (sub_bump * sub_gain);
+            processed_sample_r += (harmonic2_r + harmonic3_r + harmonic4_r + harmonic5_r + 
+                harmonic6_r + harmonic7_r + harmonic8_r + harmonic9_r + 
+                harmonic10_r + harmonic11_r + harmonic12_r + harmonic13_r + 
+                harmonic14_r)*(harmonics * 1497.00599) + (sub_bump * sub_gain);
+
+            // Scaling
+            let scale = util::db_to_gain(-21.2);
+            processed_sample_l *= scale;
+            processed_sample_r *= scale;
+
+        }
+        AlgorithmType::ABass2 | AlgorithmType::ABass2Legacy => {
+            // The first version of this fed the left input to both sides, legacy keeps that sound
+            let abass2_in_r: f32 = if h_algorithm == AlgorithmType::ABass2Legacy { harm_in_l } else { harm_in_r };
+
+            // Ardura's new Algorithm for 2024
+            processed_sample_l = custom_sincos_saturation(
+                harm_in_l, 
+                harmonics * 31.422043, 
+                harmonics * 189.29568, 
+                harmonics * 25.0, 
+                harmonics * 26.197401) + (sub_bump * sub_gain);
+            processed_sample_r = custom_sincos_saturation(
+                abass2_in_r, 
+                harmonics * 31.422043, 
+                harmonics * 189.29568, 
+                harmonics * 25.0, 
+                harmonics * 26.197401) + (sub_bump * sub_gain);
+            let h_l = (processed_sample_l * 2.0) - processed_sample_l.powf(2.0);
+            let h_r = (processed_sample_r * 2.0) - processed_sample_r.powf(2.0);
+            processed_sample_l += h_l * 0.0070118904;
+            processed_sample_r += h_r * 0.0070118904;
+            processed_sample_l = util::db_to_gain(-2.4)*processed_sample_l;
+            processed_sample_r = util::db_to_gain(-2.4)*processed_sample_r;
+        },
+        AlgorithmType::BBass => {
+            // C3 signal in RBass is C3, C4, G4, C5, E5, A#5, D6, F#6
+            processed_sample_l = b_bass_saturation(harm_in_l, harmonics) + (sub_bump * sub_gain);
+            processed_sample_r = b_bass_saturation(harm_in_r, harmonics) + (sub_bump * sub_gain);
+            processed_sample_l = util::db_to_gain(8.7)*processed_sample_l;
+            processed_sample_r = util::db_to_gain(8.7)*processed_sample_r;
+        },
+        AlgorithmType::CBass => {
+            if harmonics > 0.0 {
+                processed_sample_l = c_bass_saturation(harm_in_l, harmonics) + (sub_bump * sub_gain);
+                processed_sample_r = c_bass_saturation(harm_in_r, harmonics) + (sub_bump * sub_gain);
+            } else {
+                processed_sample_l = sub_bump * sub_gain;
+                processed_sample_r = sub_bump * sub_gain;
+            }
+        }
+        AlgorithmType::TanH => {
+            // Generate tanh curve harmonics gently
+            processed_sample_l = tape_saturation(harm_in_l, harmonics) + (sub_bump * sub_gain);
+            processed_sample_r = tape_saturation(harm_in_r, harmonics) + (sub_bump * sub_gain);
+            processed_sample_l = util::db_to_gain(8.0)*processed_sample_l;
+            processed_sample_r = util::db_to_gain(8.0)*processed_sample_r;
+        },
+        AlgorithmType::CustomSliders => {
+            processed_sample_l = custom_sincos_saturation(harm_in_l, harmonics*custom_harmonics1, harmonics*custom_harmonics2, harmonics*custom_harmonics3, harmonics*custom_harmonics4) + (sub_bump * sub_gain);
+            processed_sample_r = custom_sincos_saturation(harm_in_r, harmonics*custom_harmonics1, harmonics*custom_harmonics2, harmonics*custom_harmonics3, harmonics*custom_harmonics4) + (sub_bump * sub_gain);
+            processed_sample_l = util::db_to_gain(-4.2)*processed_sample_l;
+            processed_sample_r = util::db_to_gain(-4.2)*processed_sample_r;
+        },
+        AlgorithmType::ChebyshevBars => {
+            // Exact harmonic orders from the drawbars, scaled so the default harmonics amount is subtle
+            let [stack_l, stack_r] = ChebyshevStack::process([harm_in_l, harm_in_r], drawbar_gains);
+            processed_sample_l = stack_l * harmonics * 200.0 + (sub_bump * sub_gain);
+            processed_sample_r = stack_r * harmonics * 200.0 + (sub_bump * sub_gain);
+        },
+    }
+
+    // Hardness Saturation
+    if h_algorithm == AlgorithmType::ABass3 {
+        let leaf_wet_l: f32;
+        let leaf_wet_r: f32;
+        let threshold: f32 = util::db_to_gain(-30.0);
+        leaf_wet_l = leaf_saturation(harm_in_l, threshold, 0.5);
+        leaf_wet_r = leaf_saturation(harm_in_r, threshold, 0.5);
+        let scaler = 0.0016129*hoof_hardness*100.0; //0.0015 default;
+        processed_sample_l = scaler*leaf_wet_l + (1.0 - scaler)*processed_sample_l;
+        processed_sample_r = scaler*leaf_wet_r + (1.0 - scaler)*processed_sample_r;
+    } else {
+        processed_sample_l = chebyshev_tape(processed_sample_l, hoof_hardness);
+        processed_sample_r = chebyshev_tape(processed_sample_r, hoof_hardness);
+    }
+
+    (processed_sample_l, processed_sample_r)
+}
+
+// How much of the harmonic input an algorithm passes straight through. A Bass 3 sums its input in
+// before the scaling and hardness mix in apply_algorithm, the others only output what they add
+fn algorithm_dry(h_algorithm: AlgorithmType, harm_in_l: f32, harm_in_r: f32, settings: &HarmonicSettings) -> (f32, f32) {
+    if h_algorithm != AlgorithmType::ABass3 {
+        return (0.0, 0.0);
+    }
+    let scaler: f32 = 0.0016129*settings.hoof_hardness*100.0;
+    let dry_gain: f32 = util::db_to_gain(-21.2) * (1.0 - scaler);
+    (harm_in_l * dry_gain, harm_in_r * dry_gain)
+}
+
+// The sub by itself through an algorithm's scaling and hardness, less what the algorithm makes
+// from a silent harmonic input. SweetenX stays at rest on silence so a scratch buffer does
+fn sub_through_algorithm(h_algorithm: AlgorithmType, sub_bump: f32, sub_gain: f32, settings: &HarmonicSettings) -> f32 {
+    let mut buffer: SweetenX::Buffer = [[0.0; 2]; 8];
+    let (with_sub, _) = apply_algorithm(h_algorithm, 0.0, 0.0, sub_bump, sub_gain, settings, &mut buffer);
+    let (silent, _) = apply_algorithm(h_algorithm, 0.0, 0.0, 0.0, sub_gain, settings, &mut buffer);
+    with_sub - silent
+}
+
+// Full level inside the sub input range, fading to nothing a third of an octave outside it.
+// A knob at the end of its range turns that side off
+fn sub_range_gain(input_freq: f32, min_freq: f32, max_freq: f32) -> f32 {
+    let below: f32 = if min_freq <= SUB_RANGE_MIN_HZ { 0.0 } else { (min_freq / input_freq).log2().max(0.0) };
+    let above: f32 = if max_freq >= SUB_RANGE_MAX_HZ { 0.0 } else { (input_freq / max_freq).log2().max(0.0) };
+    (1.0 - (below + above) / SUB_RANGE_EDGE_OCTAVES).clamp(0.0, 1.0)
+}
+
+// Remove DC Offset with single pole HP
+fn dc_block(sample: f32, prev_in: &mut f32, prev_out: &mut f32) -> f32 {
+    let hp_b0: f32 = 1.0;
+    let hp_b1: f32 = -1.0;
+    let hp_a1: f32 = -0.995;
+
+    // Calculated below by Ardura in advance!
+    // double sqrt2 = 1.41421356237;
+    // double corner_frequency = 5.0 / sqrt2;
+    // double hp_gain = 1 / sqrt(1 + (5.0 / (corner_frequency)) ^ 2);
+    //let hp_gain = 0.577350269190468;
+    let hp_gain = 1.0;
+
+    let sample = hp_gain * sample;
+    let temp_sample: f32 = hp_b0 * sample + hp_b1 * *prev_in - hp_a1 * *prev_out;
+    *prev_in = sample;
+    *prev_out = temp_sample;
+    temp_sample
+}
+
 #[derive(Params)]
 struct SubhooferParams {
     /// The editor state, saved together with the parameter state so the custom scaling can be
@@ -203,6 +782,14 @@ struct SubhooferParams {
     #[persist = "editor-state"]
     editor_state: Arc<EguiState>,
 
+    /// Name of the selected GUI theme, either built in or from the user's themes.toml
+    #[persist = "theme"]
+    theme: RwLock<String>,
+
+    /// Which version of the DSP the session was saved with, see `StateMigration`
+    #[persist = "state-version"]
+    state_version: RwLock<u32>,
+
     #[id = "free_gain"]
     pub free_gain: FloatParam,
 
@@ -215,6 +802,63 @@ struct SubhooferParams {
     #[id = "Sub Drive"]
     pub sub_drive: FloatParam,
 
+    #[id = "Sub -1 Octave"]
+    pub sub_octave_1: FloatParam,
+
+    #[id = "Sub -2 Octaves"]
+    pub sub_octave_2: FloatParam,
+
+    #[id = "Sub Fifth Below"]
+    pub sub_fifth: FloatParam,
+
+    #[id = "Sub Shape"]
+    pub sub_shape: FloatParam,
+
+    #[id = "Sub Phase"]
+    pub sub_phase: FloatParam,
+
+    #[id = "Sub Invert"]
+    pub sub_invert: BoolParam,
+
+    #[id = "Sub Auto Align"]
+    pub sub_auto_align: BoolParam,
+
+    #[id = "Sub Min Input Freq"]
+    pub sub_min_freq: FloatParam,
+
+    #[id = "Sub Max Input Freq"]
+    pub sub_max_freq: FloatParam,
+
+    #[id = "Gate Threshold"]
+    pub gate_threshold: FloatParam,
+
+    #[id = "Gate Hysteresis"]
+    pub gate_hysteresis: FloatParam,
+
+    #[id = "Gate Hold"]
+    pub gate_hold: FloatParam,
+
+    #[id = "Gate Release"]
+    pub gate_release: FloatParam,
+
+    #[id = "Sub Duck"]
+    pub sub_duck: EnumParam<SubDucker::DuckTrigger>,
+
+    #[id = "Duck Every"]
+    pub duck_every: EnumParam<Modulation::SyncDivision>,
+
+    #[id = "Duck Length"]
+    pub duck_length: EnumParam<Modulation::SyncDivision>,
+
+    #[id = "Duck Depth"]
+    pub duck_depth: FloatParam,
+
+    #[id = "Duck Shape"]
+    pub duck_shape: FloatParam,
+
+    #[id = "Duck Threshold"]
+    pub duck_threshold: FloatParam,
+
     #[id = "Harmonics"]
     pub harmonics: FloatParam,
 
@@ -233,21 +877,194 @@ struct SubhooferParams {
     #[id = "Custom Strength 4"]
     pub custom_harmonics4: FloatParam,
 
+    #[nested(array, group = "Drawbar")]
+    pub drawbars: [DrawbarParams; ChebyshevStack::NUM_ORDERS],
+
     #[id = "output_gain"]
     pub output_gain: FloatParam,
 
     #[id = "dry_wet"]
     pub dry_wet: FloatParam,
+
+    #[id = "Smoothing Time"]
+    pub smoothing_time: FloatParam,
+
+    #[id = "Algorithm Crossfade"]
+    pub algorithm_crossfade: FloatParam,
+
+    #[id = "Lookahead"]
+    pub lookahead: FloatParam,
+
+    #[id = "Small Speaker"]
+    pub small_speaker: BoolParam,
+
+    #[id = "Speaker Cutoff"]
+    pub speaker_cutoff: FloatParam,
+
+    #[id = "Sub Destination"]
+    pub sub_destination: EnumParam<SubDestination>,
+
+    #[id = "Center Harmonics"]
+    pub center_harmonics: BoolParam,
+
+    #[id = "Surround Harmonics"]
+    pub surround_harmonics: BoolParam,
+
+    #[id = "Separate Sub"]
+    pub separate_sub: BoolParam,
+
+    #[id = "Separate Harmonics"]
+    pub separate_harmonics: BoolParam,
+
+    #[id = "Harmonic Tilt"]
+    pub harmonic_tilt: FloatParam,
+
+    #[id = "Harmonic High Pass"]
+    pub harmonic_high_pass: FloatParam,
+
+    #[id = "Harmonic Low Pass"]
+    pub harmonic_low_pass: FloatParam,
+
+    #[id = "Stereo Mode"]
+    pub stereo_mode: EnumParam<StereoMode>,
+
+    #[id = "Mid Harmonics"]
+    pub mid_harmonics: FloatParam,
+
+    #[id = "Side Harmonics"]
+    pub side_harmonics: FloatParam,
+
+    #[id = "Dynamic Harmonics"]
+    pub dynamic_harmonics: EnumParam<DynamicsMode>,
+
+    #[id = "Dynamics Threshold"]
+    pub dynamics_threshold: FloatParam,
+
+    #[id = "Dynamics Ratio"]
+    pub dynamics_ratio: FloatParam,
+
+    #[id = "Dynamics Attack"]
+    pub dynamics_attack: FloatParam,
+
+    #[id = "Dynamics Release"]
+    pub dynamics_release: FloatParam,
+
+    #[nested(array, group = "LFO")]
+    pub lfos: [Modulation::LfoParams; NUM_LFOS],
+
+    #[id = "Mod Env Attack"]
+    pub mod_env_attack: FloatParam,
+
+    #[id = "Mod Env Release"]
+    pub mod_env_release: FloatParam,
+
+    #[nested(array, group = "Mod Slot")]
+    pub mod_slots: [Modulation::ModSlotParams; NUM_SLOTS],
+}
+
+// Ramps for the knobs that follow the "Smoothing Time" setting instead of a fixed smoother
+struct ParamRamps {
+    hoof_hardness: LinearRamp::LinearRamp,
+    sub_gain: LinearRamp::LinearRamp,
+    sub_drive: LinearRamp::LinearRamp,
+    sub_dividers: [LinearRamp::LinearRamp; 3],
+    sub_shape: LinearRamp::LinearRamp,
+    sub_phase: LinearRamp::LinearRamp,
+    harmonics: LinearRamp::LinearRamp,
+    custom_harmonics: [LinearRamp::LinearRamp; 4],
+    drawbars: [LinearRamp::LinearRamp; ChebyshevStack::NUM_ORDERS],
+    harmonic_tilt: LinearRamp::LinearRamp,
+    // The EQ corners ramp in octaves so a sweep sounds even
+    harmonic_high_pass: LinearRamp::LinearRamp,
+    harmonic_low_pass: LinearRamp::LinearRamp,
+    mid_harmonics: LinearRamp::LinearRamp,
+    side_harmonics: LinearRamp::LinearRamp,
+}
+
+impl ParamRamps {
+    fn new(params: &SubhooferParams) -> Self {
+        Self {
+            hoof_hardness: LinearRamp::LinearRamp::new(params.hoof_hardness.value()),
+            sub_gain: LinearRamp::LinearRamp::new(params.sub_gain.value()),
+            sub_drive: LinearRamp::LinearRamp::new(params.sub_drive.value()),
+            sub_dividers: [
+                LinearRamp::LinearRamp::new(params.sub_octave_1.value()),
+                LinearRamp::LinearRamp::new(params.sub_octave_2.value()),
+                LinearRamp::LinearRamp::new(params.sub_fifth.value()),
+            ],
+            sub_shape: LinearRamp::LinearRamp::new(params.sub_shape.value()),
+            sub_phase: LinearRamp::LinearRamp::new(params.sub_phase.value()),
+            harmonics: LinearRamp::LinearRamp::new(params.harmonics.value()),
+            custom_harmonics: [
+                LinearRamp::LinearRamp::new(params.custom_harmonics1.value()),
+                LinearRamp::LinearRamp::new(params.custom_harmonics2.value()),
+                LinearRamp::LinearRamp::new(params.custom_harmonics3.value()),
+                LinearRamp::LinearRamp::new(params.custom_harmonics4.value()),
+            ],
+            drawbars: std::array::from_fn(|idx| LinearRamp::LinearRamp::new(params.drawbars[idx].gain.value())),
+            harmonic_tilt: LinearRamp::LinearRamp::new(params.harmonic_tilt.value()),
+            harmonic_high_pass: LinearRamp::LinearRamp::new(params.harmonic_high_pass.value().log2()),
+            harmonic_low_pass: LinearRamp::LinearRamp::new(params.harmonic_low_pass.value().log2()),
+            mid_harmonics: LinearRamp::LinearRamp::new(params.mid_harmonics.value()),
+            side_harmonics: LinearRamp::LinearRamp::new(params.side_harmonics.value()),
+        }
+    }
+}
+
+// One harmonic order of the Chebyshev Drawbars algorithm
+#[derive(Params)]
+struct DrawbarParams {
+    #[id = "Drawbar Gain"]
+    pub gain: FloatParam,
+
+    #[id = "Drawbar Invert"]
+    pub invert: BoolParam,
+}
+
+impl DrawbarParams {
+    fn new(order: usize) -> Self {
+        // Start with a gentle falling profile on the low orders
+        let default_gain = match order {
+            2 => 1.0,
+            3 => 0.5,
+            4 => 0.25,
+            _ => 0.0,
+        };
+        Self {
+            gain: FloatParam::new(
+                format!("Harmonic {order} Gain"),
+                default_gain,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(1))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+
+            invert: BoolParam::new(format!("Harmonic {order} Invert"), false),
+        }
+    }
 }
 
 impl Default for Subhoofer {
     fn default() -> Self {
+        let params = Arc::new(SubhooferParams::default());
         Self {
-            params: Arc::new(SubhooferParams::default()),
+            ramps: ParamRamps::new(&params),
+            current_algorithm: params.h_algorithm.value(),
+            fade_from_algorithm: params.h_algorithm.value(),
+            fade_length: 0,
+            fade_samples_left: 0,
+            params,
             out_meter_decay_weight: 1.0,
             out_meter: Arc::new(AtomicF32::new(util::MINUS_INFINITY_DB)),
             in_meter: Arc::new(AtomicF32::new(util::MINUS_INFINITY_DB)),
+            gate_open: Arc::new(AtomicBool::new(true)),
+            dynamics_envelope: Modulation::EnvelopeFollower::new(),
+            dynamics_gain: Arc::new(AtomicF32::new(1.0)),
+            scope_in: Arc::new(ScopeBuffer::new(SCOPE_SIZE)),
+            scope_out: Arc::new(ScopeBuffer::new(SCOPE_SIZE)),
             osc_gate: 0.0,
+            sub_gate: SubGate::SubGate::new(),
             lp: 0.0,
             iir_sub_bump_a: 0.0,
             iir_sub_bump_b: 0.0,
@@ -284,15 +1101,31 @@ impl Default for Subhoofer {
             iir_sample_x: 0.0,
             iir_sample_y: 0.0,
             iir_sample_z: 0.0,
-            prev_processed_in_r: 0.0,
-            prev_processed_out_r: 0.0,
-            prev_processed_in_l: 0.0,
-            prev_processed_out_l: 0.0,
             sub_iir: 0.0,
             sub_octave: false,
+            sub_octave_2: false,
+            sub_fifth: false,
+            fifth_half_cycles: 0,
             was_negative: false,
+            samples_since_crossing: 0,
+            input_period: 1000,
+            period_counting: false,
+            sub_range_gain: 1.0,
+            sub_shaper: SubShaper::SubShaper::new(),
+            sub_aligner: SubAligner::SubAligner::new(),
             bass_flip_counter: 1,
-            buffer: [0.0; 16],
+            lfos: [Modulation::Lfo::new(); NUM_LFOS],
+            mod_envelope: Modulation::EnvelopeFollower::new(),
+            host_clock: Modulation::HostClock::new(),
+            sub_ducker: SubDucker::SubDucker::new(),
+            sidechain: [0.0; BLOCK_SIZE],
+            pairs: [ChannelPair::new(); NUM_PAIRS],
+            sub_layer: [[0.0; BLOCK_SIZE]; 2],
+            harmonic_layer: [[0.0; BLOCK_SIZE]; 2],
+            aux_outputs: false,
+            prev_lfe_in: 0.0,
+            prev_lfe_out: 0.0,
+            lookahead: Lookahead::Lookahead::new(),
         }
     }
 }
@@ -301,6 +1134,8 @@ impl Default for SubhooferParams {
     fn default() -> Self {
         Self {
             editor_state: EguiState::from_size(WIDTH, HEIGHT),
+            theme: RwLock::new(theme::DEFAULT_THEME.to_string()),
+            state_version: RwLock::new(StateMigration::STATE_VERSION),
 
             // Input gain dB parameter (free as in unrestricted nums)
             free_gain: FloatParam::new(
@@ -326,7 +1161,6 @@ impl Default for SubhooferParams {
                     max: 0.30,
                 },
             )
-            .with_smoother(SmoothingStyle::Linear(30.0))
             .with_unit(" Hardness")
             .with_value_to_string(formatters::v2s_f32_percentage(4)),
 
@@ -339,7 +1173,6 @@ impl Default for SubhooferParams {
                     max: 24.0,
                 },
             )
-            .with_smoother(SmoothingStyle::Linear(30.0))
             .with_unit(" dB Sub Gain")
             .with_value_to_string(formatters::v2s_f32_rounded(1)),
 
@@ -349,17 +1182,164 @@ impl Default for SubhooferParams {
                 0.0,
                 FloatRange::Linear { min: (0.0), max: (1.0) },
             )
-            .with_smoother(SmoothingStyle::Linear(30.0))
             .with_unit("% Sub Drive")
             .with_value_to_string(formatters::v2s_f32_percentage(2)),
 
+            // Levels of each sub divider - one octave down is the original Subhoofer sub
+            sub_octave_1: FloatParam::new(
+                "Sub -1 Octave",
+                1.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(1))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+
+            sub_octave_2: FloatParam::new(
+                "Sub -2 Octaves",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(1))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+
+            // Divide by 3 for an octave and a fifth below
+            sub_fifth: FloatParam::new(
+                "Sub Fifth Below",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(1))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+
+            // Morph the sub from sine through triangle and the original square to saturated
+            sub_shape: FloatParam::new(
+                "Sub Shape",
+                SubShaper::ORIGINAL_SHAPE,
+                FloatRange::Linear { min: 0.0, max: (SubShaper::SHAPE_NAMES.len() - 1) as f32 },
+            )
+            .with_value_to_string(Arc::new(SubShaper::shape_name)),
+
+            // Phase lag added to the sub so it lines up with the dry bass
+            sub_phase: FloatParam::new(
+                "Sub Phase",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: 360.0 },
+            )
+            .with_unit("°")
+            .with_value_to_string(formatters::v2s_f32_rounded(0)),
+
+            sub_invert: BoolParam::new("Sub Invert", false),
+
+            // Measure how far the sub lags the dry low band and make it up with the allpass
+            sub_auto_align: BoolParam::new("Sub Auto Align", false),
+
+            // Input notes outside this range fade the sub out
+            sub_min_freq: FloatParam::new(
+                "Sub Min Input Freq",
+                SUB_RANGE_MIN_HZ,
+                FloatRange::Skewed {
+                    min: SUB_RANGE_MIN_HZ,
+                    max: 200.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(1))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+
+            sub_max_freq: FloatParam::new(
+                "Sub Max Input Freq",
+                SUB_RANGE_MAX_HZ,
+                FloatRange::Skewed {
+                    min: 50.0,
+                    max: SUB_RANGE_MAX_HZ,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+
+            // Gate on the sub's input so noise between notes doesn't flip the dividers. All the way
+            // down it never closes
+            gate_threshold: FloatParam::new(
+                "Gate Threshold",
+                -60.0,
+                FloatRange::Linear { min: util::MINUS_INFINITY_DB, max: 0.0 },
+            )
+            .with_unit(" dBFS")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+
+            gate_hysteresis: FloatParam::new(
+                "Gate Hysteresis",
+                6.0,
+                FloatRange::Linear { min: 0.0, max: 24.0 },
+            )
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+
+            gate_hold: FloatParam::new(
+                "Gate Hold",
+                50.0,
+                FloatRange::Linear { min: 0.0, max: 500.0 },
+            )
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(0)),
+
+            gate_release: FloatParam::new(
+                "Gate Release",
+                100.0,
+                FloatRange::Skewed {
+                    min: 1.0,
+                    max: 1000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(0)),
+
+            // Duck only the sub under a kick, on the host's beat grid or from the sidechain input
+            sub_duck: EnumParam::new("Sub Duck", SubDucker::DuckTrigger::Off),
+
+            duck_every: EnumParam::new("Duck Every", Modulation::SyncDivision::Quarter),
+
+            duck_length: EnumParam::new("Duck Length", Modulation::SyncDivision::Eighth),
+
+            duck_depth: FloatParam::new(
+                "Duck Depth",
+                0.8,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+
+            duck_shape: FloatParam::new(
+                "Duck Shape",
+                0.5,
+                FloatRange::Linear { min: 0.0, max: 1.0 },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+
+            duck_threshold: FloatParam::new(
+                "Duck Threshold",
+                -24.0,
+                FloatRange::Linear { min: -60.0, max: 0.0 },
+            )
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+
             // Harmonics Parameter
             harmonics: FloatParam::new(
                 "Harmonics",
                 0.000580,
                 FloatRange::Skewed { min: 0.0, max: 1.0, factor: FloatRange::skew_factor(-2.8) }
             )
-            .with_smoother(SmoothingStyle::Linear(30.0))
             .with_unit(" Harmonics")
             .with_value_to_string(formatters::v2s_f32_percentage(4)),
 
@@ -372,7 +1352,6 @@ impl Default for SubhooferParams {
                 0.0,
                 FloatRange::Skewed { min: 0.0, max: 400.0, factor: FloatRange::skew_factor(-2.0) }
             )
-            .with_smoother(SmoothingStyle::Linear(30.0))
             .with_unit(" Custom Harmonic 1"),
 
             // Custom Harmonics Parameter 2
@@ -381,7 +1360,6 @@ impl Default for SubhooferParams {
                 0.0,
                 FloatRange::Skewed { min: 0.0, max: 400.0, factor: FloatRange::skew_factor(-2.0) }
             )
-            .with_smoother(SmoothingStyle::Linear(30.0))
             .with_unit(" Custom Harmonic 2"),
 
             // Custom Harmonics Parameter 3
@@ -390,7 +1368,6 @@ impl Default for SubhooferParams {
                 0.0,
                 FloatRange::Skewed { min: 0.0, max: 400.0, factor: FloatRange::skew_factor(-2.0) }
             )
-            .with_smoother(SmoothingStyle::Linear(30.0))
             .with_unit(" Custom Harmonic 3"),
 
             // Custom Harmonics Parameter 4
@@ -399,9 +1376,11 @@ impl Default for SubhooferParams {
                 0.0,
                 FloatRange::Skewed { min: 0.0, max: 400.0, factor: FloatRange::skew_factor(-2.0) }
             )
-            .with_smoother(SmoothingStyle::Linear(30.0))
             .with_unit(" Custom Harmonic 4"),
 
+            // Chebyshev drawbars for harmonics 2 through 16
+            drawbars: std::array::from_fn(|idx| DrawbarParams::new(idx + ChebyshevStack::FIRST_ORDER)),
+
             // Output gain parameter
             output_gain: FloatParam::new(
                 "Output Gain",
@@ -430,28 +1409,980 @@ impl Default for SubhooferParams {
             .with_unit("% Wet")
             .with_value_to_string(formatters::v2s_f32_percentage(2))
             .with_string_to_value(formatters::s2v_f32_percentage()),
-        }
-    }
-}
-
-impl Plugin for Subhoofer {
-    const NAME: &'static str = "Subhoofer";
-    const VENDOR: &'static str = "Ardura";
-    const URL: &'static str = "https://github.com/ardura";
-    const EMAIL: &'static str = "azviscarra@gmail.com";
 
-    const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+            // Ramp time for hardness, harmonics, sub, custom/drawbar, harmonic EQ and mid/side knobs
+            smoothing_time: FloatParam::new(
+                "Smoothing Time",
+                30.0,
+                FloatRange::Skewed {
+                    min: 0.0,
+                    max: 500.0,
+                    factor: FloatRange::skew_factor(-1.5),
+                },
+            )
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
 
-    // This looks like it's flexible for running the plugin in mono or stereo
-    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[
-        AudioIOLayout {main_input_channels: NonZeroU32::new(2), main_output_channels: NonZeroU32::new(2), ..AudioIOLayout::const_default()},
-        AudioIOLayout {main_input_channels: NonZeroU32::new(1), main_output_channels: NonZeroU32::new(1), ..AudioIOLayout::const_default()},
-    ];
+            // Time to blend between algorithms when switching
+            algorithm_crossfade: FloatParam::new(
+                "Algorithm Crossfade",
+                20.0,
+                FloatRange::Skewed {
+                    min: 0.0,
+                    max: 200.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
 
-    const SAMPLE_ACCURATE_AUTOMATION: bool = true;
+            // Lets the sub gate open before notes play, adds this much latency
+            lookahead: FloatParam::new(
+                "Lookahead",
+                0.0,
+                FloatRange::Linear { min: 0.0, max: Lookahead::MAX_LOOKAHEAD_MS },
+            )
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
 
-    type SysExMessage = ();
-    type BackgroundTask = ();
+            // Small speaker mode removes the fundamental below the cutoff and keeps the harmonics
+            small_speaker: BoolParam::new("Small Speaker", false),
+
+            // Speaker cutoff Hz parameter
+            speaker_cutoff: FloatParam::new(
+                "Speaker Cutoff",
+                120.0,
+                FloatRange::Skewed {
+                    min: 40.0,
+                    max: 300.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_smoother(SmoothingStyle::Logarithmic(30.0))
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+
+            // Where the sub goes in 5.1 and 7.1, stereo and mono always use the mains
+            sub_destination: EnumParam::new("Sub Destination", SubDestination::Mains),
+
+            // Surround channels that get harmonics and feed the sub, the fronts always do
+            center_harmonics: BoolParam::new("Center Harmonics", false),
+            surround_harmonics: BoolParam::new("Surround Harmonics", false),
+
+            // Leave the sub or harmonics out of the main output when they're used from the aux outputs
+            separate_sub: BoolParam::new("Separate Sub", false),
+            separate_harmonics: BoolParam::new("Separate Harmonics", false),
+
+            // Tone shaping that only touches the generated harmonics
+            harmonic_tilt: FloatParam::new(
+                "Harmonic Tilt",
+                0.0,
+                FloatRange::Linear { min: -12.0, max: 12.0 },
+            )
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+
+            harmonic_high_pass: FloatParam::new(
+                "Harmonic High Pass",
+                HARMONIC_EQ_MIN_HZ,
+                FloatRange::Skewed {
+                    min: HARMONIC_EQ_MIN_HZ,
+                    max: 1000.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(0))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+
+            harmonic_low_pass: FloatParam::new(
+                "Harmonic Low Pass",
+                HARMONIC_EQ_MAX_HZ,
+                FloatRange::Skewed {
+                    min: 500.0,
+                    max: HARMONIC_EQ_MAX_HZ,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(" Hz")
+            .with_value_to_string(formatters::v2s_f32_hz_then_khz(1))
+            .with_string_to_value(formatters::s2v_f32_hz_then_khz()),
+
+            // Which part of the stereo image the harmonics are generated from
+            stereo_mode: EnumParam::new("Stereo Mode", StereoMode::LeftRight),
+
+            // Mid+Side depths, the sub always stays in the center and isn't affected
+            mid_harmonics: FloatParam::new(
+                "Mid Harmonics",
+                1.0,
+                FloatRange::Linear { min: 0.0, max: 2.0 },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+
+            side_harmonics: FloatParam::new(
+                "Side Harmonics",
+                1.0,
+                FloatRange::Linear { min: 0.0, max: 2.0 },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+
+            // Raise the harmonics as the input falls below or rises above the threshold
+            dynamic_harmonics: EnumParam::new("Dynamic Harmonics", DynamicsMode::Off),
+
+            dynamics_threshold: FloatParam::new(
+                "Dynamics Threshold",
+                -24.0,
+                FloatRange::Linear { min: -60.0, max: 0.0 },
+            )
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+
+            dynamics_ratio: FloatParam::new(
+                "Dynamics Ratio",
+                2.0,
+                FloatRange::Skewed {
+                    min: 1.0,
+                    max: 10.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(":1")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+
+            dynamics_attack: FloatParam::new(
+                "Dynamics Attack",
+                10.0,
+                FloatRange::Skewed {
+                    min: 0.1,
+                    max: 200.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+
+            dynamics_release: FloatParam::new(
+                "Dynamics Release",
+                150.0,
+                FloatRange::Skewed {
+                    min: 5.0,
+                    max: 2000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(0)),
+
+            lfos: std::array::from_fn(|idx| Modulation::LfoParams::new(idx + 1)),
+
+            // The mod matrix envelope follower, separate from Dynamic Harmonics
+            mod_env_attack: FloatParam::new(
+                "Mod Env Attack",
+                10.0,
+                FloatRange::Skewed {
+                    min: 0.1,
+                    max: 200.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+
+            mod_env_release: FloatParam::new(
+                "Mod Env Release",
+                150.0,
+                FloatRange::Skewed {
+                    min: 5.0,
+                    max: 2000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(0)),
+
+            mod_slots: std::array::from_fn(|idx| Modulation::ModSlotParams::new(idx + 1)),
+        }
+    }
+}
+
+impl Subhoofer {
+    /// Process up to `BLOCK_SIZE` samples in place for any of the supported layouts. Smoothed values
+    /// are filled for the whole block first, then each stage runs over the block with pairs of
+    /// channels side by side. This is public so the benches can drive the DSP without a host.
+    pub fn process_block(&mut self, channels: &mut [&mut [f32]], sample_rate: f32) {
+        let block_len: usize = channels.iter().map(|channel| channel.len()).min().unwrap_or(0).min(BLOCK_SIZE);
+
+        // These knobs ramp over the Smoothing Time setting
+        let ramp_samples: u32 = (self.params.smoothing_time.value() * 0.001 * sample_rate) as u32;
+        let ramps = &mut self.ramps;
+
+        let mut gain: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+        let mut output_gain: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+        let mut speaker_cutoff: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+        let mut hoof_hardness: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+        let mut sub_gain: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+        let mut sub_drive: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+        let mut harmonics: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+        let mut custom_harmonics: [[f32; BLOCK_SIZE]; 4] = [[0.0; BLOCK_SIZE]; 4];
+        let mut drawbar_gains: [[f32; ChebyshevStack::NUM_ORDERS]; BLOCK_SIZE] = [[0.0; ChebyshevStack::NUM_ORDERS]; BLOCK_SIZE];
+
+        self.params.free_gain.smoothed.next_block(&mut gain, block_len);
+        self.params.output_gain.smoothed.next_block(&mut output_gain, block_len);
+        self.params.speaker_cutoff.smoothed.next_block(&mut speaker_cutoff, block_len);
+
+        // The EQ corners come out of the ramps in octaves
+        let mut harmonic_tilt: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+        let mut harmonic_high_pass: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+        let mut harmonic_low_pass: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+        ramps.harmonic_tilt.next_block(&mut harmonic_tilt[..block_len], self.params.harmonic_tilt.value(), ramp_samples);
+        ramps.harmonic_high_pass.next_block(&mut harmonic_high_pass[..block_len], self.params.harmonic_high_pass.value().log2(), ramp_samples);
+        ramps.harmonic_low_pass.next_block(&mut harmonic_low_pass[..block_len], self.params.harmonic_low_pass.value().log2(), ramp_samples);
+
+        // The harmonic EQ is skipped while it's all the way open for the whole block
+        let open_high_pass: f32 = HARMONIC_EQ_MIN_HZ.log2();
+        let open_low_pass: f32 = HARMONIC_EQ_MAX_HZ.log2();
+        let tone_active: bool = (0..block_len).any(|idx| {
+            harmonic_tilt[idx] != 0.0 || harmonic_high_pass[idx] > open_high_pass || harmonic_low_pass[idx] < open_low_pass
+        });
+        let mut mid_harmonics: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+        let mut side_harmonics: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+        ramps.mid_harmonics.next_block(&mut mid_harmonics[..block_len], self.params.mid_harmonics.value(), ramp_samples);
+        ramps.side_harmonics.next_block(&mut side_harmonics[..block_len], self.params.side_harmonics.value(), ramp_samples);
+        ramps.hoof_hardness.next_block(&mut hoof_hardness[..block_len], self.params.hoof_hardness.value(), ramp_samples);
+        ramps.sub_gain.next_block(&mut sub_gain[..block_len], self.params.sub_gain.value(), ramp_samples);
+        ramps.sub_drive.next_block(&mut sub_drive[..block_len], self.params.sub_drive.value(), ramp_samples);
+        let mut sub_dividers: [[f32; BLOCK_SIZE]; 3] = [[0.0; BLOCK_SIZE]; 3];
+        ramps.sub_dividers[0].next_block(&mut sub_dividers[0][..block_len], self.params.sub_octave_1.value(), ramp_samples);
+        ramps.sub_dividers[1].next_block(&mut sub_dividers[1][..block_len], self.params.sub_octave_2.value(), ramp_samples);
+        ramps.sub_dividers[2].next_block(&mut sub_dividers[2][..block_len], self.params.sub_fifth.value(), ramp_samples);
+        let mut sub_shape: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+        ramps.sub_shape.next_block(&mut sub_shape[..block_len], self.params.sub_shape.value(), ramp_samples);
+        let mut sub_phase: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+        ramps.sub_phase.next_block(&mut sub_phase[..block_len], self.params.sub_phase.value(), ramp_samples);
+        ramps.harmonics.next_block(&mut harmonics[..block_len], self.params.harmonics.value(), ramp_samples);
+        ramps.custom_harmonics[0].next_block(&mut custom_harmonics[0][..block_len], self.params.custom_harmonics1.value(), ramp_samples);
+        ramps.custom_harmonics[1].next_block(&mut custom_harmonics[1][..block_len], self.params.custom_harmonics2.value(), ramp_samples);
+        ramps.custom_harmonics[2].next_block(&mut custom_harmonics[2][..block_len], self.params.custom_harmonics3.value(), ramp_samples);
+        ramps.custom_harmonics[3].next_block(&mut custom_harmonics[3][..block_len], self.params.custom_harmonics4.value(), ramp_samples);
+
+        // These only change between blocks
+        let h_algorithm: AlgorithmType = self.params.h_algorithm.value();
+        let mut dry_wet: [f32; BLOCK_SIZE] = [self.params.dry_wet.value(); BLOCK_SIZE];
+        let small_speaker: bool = self.params.small_speaker.value();
+        let stereo_mode: StereoMode = self.params.stereo_mode.value();
+
+        let mut overall_scale: f32 = 1.0;
+        overall_scale /= 44100.0;
+        overall_scale *= sample_rate;
+
+        // The drawbars only ramp while something can hear them, otherwise they sit on their targets
+        let drawbars_active: bool = h_algorithm == AlgorithmType::ChebyshevBars
+            || self.current_algorithm == AlgorithmType::ChebyshevBars
+            || (self.fade_samples_left > 0 && self.fade_from_algorithm == AlgorithmType::ChebyshevBars);
+        for (order, (ramp, drawbar)) in ramps.drawbars.iter_mut().zip(self.params.drawbars.iter()).enumerate() {
+            if !drawbars_active {
+                ramp.reset(drawbar.gain.value());
+                continue;
+            }
+            let polarity: f32 = if drawbar.invert.value() { -1.0 } else { 1.0 };
+            for gains in drawbar_gains[..block_len].iter_mut() {
+                gains[order] = polarity * ramp.next(drawbar.gain.value(), ramp_samples);
+            }
+        }
+
+        // Which channel pairs are in this layout and which ones get harmonics. Fronts always do
+        let pairs = pair_channels(channels.len());
+        let has_lfe: bool = channels.len() > LFE_CHANNEL && pairs[CENTER_PAIR].is_some();
+        // The LFE channel's own signal skips the gains and the detectors, it's only delayed to stay in line
+        let lfe_channel: Option<usize> = if has_lfe { Some(LFE_CHANNEL) } else { None };
+        let pair_enabled: [bool; NUM_PAIRS] = [
+            true,
+            self.params.center_harmonics.value(),
+            self.params.surround_harmonics.value(),
+            self.params.surround_harmonics.value(),
+        ];
+
+        // Layers for the aux outputs, silent unless the front pair fills them below. Without the
+        // aux outputs (surround, or a host that didn't give them to us) nothing is left out of the mains
+        let separate_sub: bool = self.aux_outputs && self.params.separate_sub.value();
+        let separate_harmonics: bool = self.aux_outputs && self.params.separate_harmonics.value();
+        self.sub_layer = [[0.0; BLOCK_SIZE]; 2];
+        self.harmonic_layer = [[0.0; BLOCK_SIZE]; 2];
+
+        // Without an LFE channel the sub always goes back into the mains
+        let sub_destination: SubDestination = if has_lfe { self.params.sub_destination.value() } else { SubDestination::Mains };
+        let sub_to_mains: bool = sub_destination != SubDestination::Lfe;
+        let sub_to_lfe: bool = sub_destination != SubDestination::Mains;
+
+        // Input gain on every channel but the LFE
+        for (channel_idx, channel) in channels.iter_mut().enumerate() {
+            if Some(channel_idx) == lfe_channel {
+                continue;
+            }
+            for (sample, sample_gain) in channel[..block_len].iter_mut().zip(gain.iter()) {
+                *sample *= sample_gain;
+            }
+        }
+
+        // The sub gate hears the input ahead of everything else so it's open by the time a note
+        // plays. The sub itself, the envelopes and the dry signal all come out of the delay together
+        let mut gate_source: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+        for (pair, enabled) in pairs.iter().zip(pair_enabled.iter()) {
+            if let (Some((lane_l, lane_r)), true) = (*pair, *enabled) {
+                for idx in 0..block_len {
+                    gate_source[idx] += channels[lane_l][idx] + channels[lane_r][idx];
+                }
+            }
+        }
+        self.lookahead.process(channels, block_len);
+
+        // Mod matrix - the sources move the knobs from where they're set in normalized steps
+        let beats_per_sample: f64 = self.host_clock.beats_per_sample(sample_rate);
+        if self.params.mod_slots.iter().any(|slot| slot.source.value() != ModSource::Off && slot.depth.value() != 0.0) {
+            self.mod_envelope.set(self.params.mod_env_attack.value(), self.params.mod_env_release.value(), sample_rate);
+            for idx in 0..block_len {
+                let beats: Option<f64> = self.host_clock.beats.map(|beats| beats + idx as f64 * beats_per_sample);
+                let peak: f32 = channels.iter().enumerate()
+                    .filter(|(channel_idx, _)| Some(*channel_idx) != lfe_channel)
+                    .fold(0.0, |peak: f32, (_, channel)| peak.max(channel[idx].abs()));
+                let lfo_1: f32 = self.lfos[0].next(&self.params.lfos[0], &self.host_clock, beats, sample_rate);
+                let lfo_2: f32 = self.lfos[1].next(&self.params.lfos[1], &self.host_clock, beats, sample_rate);
+                let envelope: f32 = self.mod_envelope.next(peak);
+
+                let mut offsets: [f32; NUM_TARGETS] = [0.0; NUM_TARGETS];
+                for slot in self.params.mod_slots.iter() {
+                    let source: f32 = match slot.source.value() {
+                        ModSource::Off => 0.0,
+                        ModSource::Lfo1 => lfo_1,
+                        ModSource::Lfo2 => lfo_2,
+                        ModSource::Envelope => envelope,
+                    };
+                    offsets[slot.target.value() as usize] += source * slot.depth.value();
+                }
+
+                harmonics[idx] = Modulation::modulate(&self.params.harmonics, harmonics[idx], offsets[ModTarget::Harmonics as usize]);
+                hoof_hardness[idx] = Modulation::modulate(&self.params.hoof_hardness, hoof_hardness[idx], offsets[ModTarget::Hardness as usize]);
+                sub_gain[idx] = Modulation::modulate(&self.params.sub_gain, sub_gain[idx], offsets[ModTarget::SubGain as usize]);
+                sub_drive[idx] = Modulation::modulate(&self.params.sub_drive, sub_drive[idx], offsets[ModTarget::SubDrive as usize]);
+                dry_wet[idx] = Modulation::modulate(&self.params.dry_wet, dry_wet[idx], offsets[ModTarget::DryWet as usize]);
+            }
+        }
+
+        // Sub ducking gain, worked out before the clock moves on to the next block
+        let mut duck_gain: [f32; BLOCK_SIZE] = [1.0; BLOCK_SIZE];
+        let duck_settings = SubDucker::DuckSettings {
+            trigger: self.params.sub_duck.value(),
+            every: self.params.duck_every.value().beats(),
+            length: self.params.duck_length.value().beats(),
+            depth: self.params.duck_depth.value(),
+            shape: self.params.duck_shape.value(),
+            threshold_db: self.params.duck_threshold.value(),
+        };
+        if duck_settings.trigger != SubDucker::DuckTrigger::Off {
+            for idx in 0..block_len {
+                let beats: Option<f64> = self.host_clock.beats.map(|beats| beats + idx as f64 * beats_per_sample);
+                duck_gain[idx] = self.sub_ducker.next(&duck_settings, beats, beats_per_sample, self.sidechain[idx]);
+            }
+        }
+        self.host_clock.beats = self.host_clock.beats.map(|beats| beats + block_len as f64 * beats_per_sample);
+
+        // Dynamic Harmonics follows the loudest channel but the LFE and scales the harmonics for every algorithm
+        let dynamics_mode: DynamicsMode = self.params.dynamic_harmonics.value();
+        if dynamics_mode != DynamicsMode::Off {
+            let threshold: f32 = self.params.dynamics_threshold.value();
+            let slope: f32 = 1.0 - self.params.dynamics_ratio.value().recip();
+            self.dynamics_envelope.set(self.params.dynamics_attack.value(), self.params.dynamics_release.value(), sample_rate);
+            for idx in 0..block_len {
+                let peak: f32 = channels.iter().enumerate()
+                    .filter(|(channel_idx, _)| Some(*channel_idx) != lfe_channel)
+                    .fold(0.0, |peak: f32, (_, channel)| peak.max(channel[idx].abs()));
+                let envelope_db: f32 = self.dynamics_envelope.next_db(peak);
+                let distance_db: f32 = match dynamics_mode {
+                    DynamicsMode::BoostQuiet => threshold - envelope_db,
+                    _ => envelope_db - threshold,
+                };
+                let boost_db: f32 = (distance_db.max(0.0) * slope).min(MAX_DYNAMIC_HARMONICS_DB);
+                harmonics[idx] *= util::db_to_gain_fast(boost_db);
+                if idx + 1 == block_len {
+                    self.dynamics_gain.store(util::db_to_gain_fast(boost_db), std::sync::atomic::Ordering::Relaxed);
+                }
+            }
+        } else {
+            self.dynamics_envelope.reset();
+            self.dynamics_gain.store(1.0, std::sync::atomic::Ordering::Relaxed);
+        }
+
+        // The sub is built from every channel that gets harmonics
+        let mut sub_source: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+        for (pair, enabled) in pairs.iter().zip(pair_enabled.iter()) {
+            if let (Some((lane_l, lane_r)), true) = (*pair, *enabled) {
+                for idx in 0..block_len {
+                    sub_source[idx] += channels[lane_l][idx] + channels[lane_r][idx];
+                }
+            }
+        }
+
+        // The sub and crossfade carry state from sample to sample so they stay serial
+        let sub_invert: bool = self.params.sub_invert.value();
+        let auto_align: bool = self.params.sub_auto_align.value();
+        let min_freq: f32 = self.params.sub_min_freq.value();
+        let max_freq: f32 = self.params.sub_max_freq.value();
+        self.sub_gate.set(
+            self.params.gate_threshold.value(),
+            self.params.gate_hysteresis.value(),
+            self.params.gate_hold.value(),
+            self.params.gate_release.value(),
+            sample_rate,
+        );
+        self.sub_shaper.set_sample_rate(sample_rate);
+        let mut sub_bump: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+        for idx in 0..block_len {
+            let sub_settings = SubSettings {
+                hoof_hardness: hoof_hardness[idx],
+                sub_gain: sub_gain[idx],
+                sub_drive: sub_drive[idx],
+                divider_gains: [sub_dividers[0][idx], sub_dividers[1][idx], sub_dividers[2][idx]],
+                sub_shape: sub_shape[idx],
+                sub_phase: sub_phase[idx],
+                sub_invert,
+                auto_align,
+                min_freq,
+                max_freq,
+                overall_scale,
+            };
+            sub_bump[idx] = self.process_sub(sub_source[idx], gate_source[idx], &sub_settings) * duck_gain[idx];
+        }
+        self.gate_open.store(self.sub_gate.is_open(), std::sync::atomic::Ordering::Relaxed);
+
+        // Crossfade from the previous algorithm on a change so the different makeup gains don't click.
+        // A change during a fade waits for it to finish so the blend never jumps. The outgoing
+        // algorithm carries on from the SweetenX state it had on its own copy
+        if h_algorithm != self.current_algorithm && self.fade_samples_left == 0 {
+            self.fade_from_algorithm = self.current_algorithm;
+            self.current_algorithm = h_algorithm;
+            self.fade_length = (self.params.algorithm_crossfade.value() * 0.001 * sample_rate) as u32;
+            self.fade_samples_left = self.fade_length;
+            for pair in self.pairs.iter_mut() {
+                pair.fade_buffer = pair.buffer;
+            }
+        }
+        let current_algorithm: AlgorithmType = self.current_algorithm;
+        let fading: bool = self.fade_samples_left > 0;
+        let mut fade_amount: [f32; BLOCK_SIZE] = [1.0; BLOCK_SIZE];
+        for amount in fade_amount[..block_len].iter_mut() {
+            if self.fade_samples_left > 0 {
+                *amount = 1.0 - self.fade_samples_left as f32 / self.fade_length as f32;
+                self.fade_samples_left -= 1;
+            }
+        }
+
+        // Settings for every sample of the block, shared by all pairs. Built after the
+        // mod matrix and Dynamic Harmonics have moved the knobs
+        let harmonic_settings: [HarmonicSettings; BLOCK_SIZE] = std::array::from_fn(|idx| HarmonicSettings {
+            harmonics: harmonics[idx],
+            hoof_hardness: hoof_hardness[idx],
+            custom_harmonics1: custom_harmonics[0][idx],
+            custom_harmonics2: custom_harmonics[1][idx],
+            custom_harmonics3: custom_harmonics[2][idx],
+            custom_harmonics4: custom_harmonics[3][idx],
+            drawbar_gains: drawbar_gains[idx],
+            overall_scale,
+        });
+
+        // Dry input of the front pair for the meters and scope
+        let mut front_in_l: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+        let mut front_in_r: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+
+        for (pair_idx, pair) in pairs.iter().enumerate() {
+            let Some((lane_l, lane_r)) = *pair else { continue; };
+            let state = &mut self.pairs[pair_idx];
+
+            let mut in_l: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+            let mut in_r: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+            in_l[..block_len].copy_from_slice(&channels[lane_l][..block_len]);
+            in_r[..block_len].copy_from_slice(&channels[lane_r][..block_len]);
+            if pair_idx == FRONT_PAIR {
+                front_in_l = in_l;
+                front_in_r = in_r;
+            }
+
+            let mut processed_l: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+            let mut processed_r: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+            if pair_enabled[pair_idx] {
+                // Small speaker mode builds the harmonics only from what the speaker can't play
+                let mut harm_in_l: [f32; BLOCK_SIZE] = in_l;
+                let mut harm_in_r: [f32; BLOCK_SIZE] = in_r;
+                if small_speaker {
+                    for idx in 0..block_len {
+                        for filter in state.speaker_split_l.iter_mut().chain(state.speaker_split_r.iter_mut()) {
+                            filter.set(sample_rate, speaker_cutoff[idx], BiquadFilters::BUTTERWORTH_Q, 0.0);
+                        }
+                        harm_in_l[idx] = state.speaker_split_l.iter_mut().fold(in_l[idx], |sample, filter| filter.process(sample));
+                        harm_in_r[idx] = state.speaker_split_r.iter_mut().fold(in_r[idx], |sample, filter| filter.process(sample));
+                    }
+                }
+                for idx in 0..block_len {
+                    (harm_in_l[idx], harm_in_r[idx]) = encode_stereo(stereo_mode, harm_in_l[idx], harm_in_r[idx]);
+                }
+
+                // Only the front pair carries the sub in the mains
+                let pair_sub: f32 = if pair_idx == FRONT_PAIR && sub_to_mains { 1.0 } else { 0.0 };
+
+                // The sub and harmonics are kept apart for the harmonic EQ, the mid/side modes and the front pair's aux outputs
+                let split_layers: bool = tone_active
+                    || stereo_mode != StereoMode::LeftRight
+                    || (pair_idx == FRONT_PAIR && self.aux_outputs);
+
+                // SweetenX only sees the harmonic input, so a copy of its buffer stays in step
+                // for working out the layer without the sub
+                let mut no_sub_buffer: SweetenX::Buffer = state.buffer;
+                let mut no_sub_fade_buffer: SweetenX::Buffer = state.fade_buffer;
+
+                // Add: Original signal + Harmonics + Sub signal. Each pass runs a whole block
+                let pair_sub_bump: [f32; BLOCK_SIZE] = std::array::from_fn(|idx| sub_bump[idx] * pair_sub);
+                let no_sub: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+                let harm_in: (&[f32; BLOCK_SIZE], &[f32; BLOCK_SIZE]) = (&harm_in_l, &harm_in_r);
+                let wet = apply_algorithm_block(current_algorithm, harm_in, &pair_sub_bump, &sub_gain, &harmonic_settings, block_len, &mut state.buffer);
+                let wet_old = if fading {
+                    apply_algorithm_block(self.fade_from_algorithm, harm_in, &pair_sub_bump, &sub_gain, &harmonic_settings, block_len, &mut state.fade_buffer)
+                } else {
+                    wet
+                };
+                let no_sub_wet = if split_layers {
+                    apply_algorithm_block(current_algorithm, harm_in, &no_sub, &sub_gain, &harmonic_settings, block_len, &mut no_sub_buffer)
+                } else {
+                    wet
+                };
+                let no_sub_wet_old = if split_layers && fading {
+                    apply_algorithm_block(self.fade_from_algorithm, harm_in, &no_sub, &sub_gain, &harmonic_settings, block_len, &mut no_sub_fade_buffer)
+                } else {
+                    no_sub_wet
+                };
+
+                for idx in 0..block_len {
+                    let settings: &HarmonicSettings = &harmonic_settings[idx];
+
+                    let (old_l, old_r) = (wet_old.0[idx], wet_old.1[idx]);
+                    let processed_sample_l: f32 = old_l + (wet.0[idx] - old_l) * fade_amount[idx];
+                    let processed_sample_r: f32 = old_r + (wet.1[idx] - old_r) * fade_amount[idx];
+
+                    // Remove DC Offset with single pole HP
+                    processed_l[idx] = dc_block(processed_sample_l, &mut state.prev_processed_in_l, &mut state.prev_processed_out_l);
+                    processed_r[idx] = dc_block(processed_sample_r, &mut state.prev_processed_in_r, &mut state.prev_processed_out_r);
+
+                    if split_layers {
+                        let (old_l, old_r) = (no_sub_wet_old.0[idx], no_sub_wet_old.1[idx]);
+                        let mut harmonic_l: f32 = old_l + (no_sub_wet.0[idx] - old_l) * fade_amount[idx];
+                        let mut harmonic_r: f32 = old_r + (no_sub_wet.1[idx] - old_r) * fade_amount[idx];
+                        harmonic_l = dc_block(harmonic_l, &mut state.prev_harmonic_in_l, &mut state.prev_harmonic_out_l);
+                        harmonic_r = dc_block(harmonic_r, &mut state.prev_harmonic_in_r, &mut state.prev_harmonic_out_r);
+
+                        // The sub layer is whatever the sub added so the layers sum back to the full signal
+                        let mut sub_l: f32 = processed_l[idx] - harmonic_l;
+                        let mut sub_r: f32 = processed_r[idx] - harmonic_r;
+
+                        // A Bass 3's copy of its input is neither layer, it stays in the mains as it is
+                        let (mut dry_l, mut dry_r) = algorithm_dry(current_algorithm, harm_in_l[idx], harm_in_r[idx], settings);
+                        if fading {
+                            let (old_l, old_r) = algorithm_dry(self.fade_from_algorithm, harm_in_l[idx], harm_in_r[idx], settings);
+                            dry_l = old_l + (dry_l - old_l) * fade_amount[idx];
+                            dry_r = old_r + (dry_r - old_r) * fade_amount[idx];
+                        }
+                        harmonic_l -= dry_l;
+                        harmonic_r -= dry_r;
+
+                        // Mid and side lanes go back to L/R with the sub from the mid lane in the center
+                        if stereo_mode != StereoMode::LeftRight {
+                            (harmonic_l, harmonic_r) = decode_stereo(stereo_mode, harmonic_l, harmonic_r, mid_harmonics[idx], side_harmonics[idx]);
+                            (dry_l, dry_r) = decode_stereo(StereoMode::MidSide, dry_l, dry_r, 1.0, 1.0);
+                            sub_r = sub_l;
+                        }
+
+                        // Shape only the harmonics then put the sub back
+                        if tone_active {
+                            state.set_harmonic_eq(sample_rate, harmonic_high_pass[idx].exp2(), harmonic_low_pass[idx].exp2(), harmonic_tilt[idx]);
+                            harmonic_l = state.harmonic_eq_l.iter_mut().fold(harmonic_l, |sample, filter| filter.process(sample));
+                            harmonic_r = state.harmonic_eq_r.iter_mut().fold(harmonic_r, |sample, filter| filter.process(sample));
+                        }
+                        processed_l[idx] = harmonic_l + sub_l + dry_l;
+                        processed_r[idx] = harmonic_r + sub_r + dry_r;
+
+                        if pair_idx == FRONT_PAIR {
+                            self.harmonic_layer[0][idx] = harmonic_l;
+                            self.harmonic_layer[1][idx] = harmonic_r;
+                            self.sub_layer[0][idx] = sub_l;
+                            self.sub_layer[1][idx] = sub_r;
+
+                            // Take out what's routed to the aux outputs instead
+                            if separate_sub {
+                                processed_l[idx] -= sub_l;
+                                processed_r[idx] -= sub_r;
+                            }
+                            if separate_harmonics {
+                                processed_l[idx] -= harmonic_l;
+                                processed_r[idx] -= harmonic_r;
+                            }
+                        }
+                    }
+                }
+            }
+
+            // Calculate dry/wet mix
+            for idx in 0..block_len {
+                processed_l[idx] = in_l[idx] + processed_l[idx] * dry_wet[idx];
+                processed_r[idx] = in_r[idx] + processed_r[idx] * dry_wet[idx];
+            }
+
+            // Now take out what the small speaker can't reproduce, leaving the harmonics of it
+            if small_speaker && pair_enabled[pair_idx] {
+                for idx in 0..block_len {
+                    for filter in state.speaker_hp_l.iter_mut().chain(state.speaker_hp_r.iter_mut()) {
+                        filter.set(sample_rate, speaker_cutoff[idx], BiquadFilters::BUTTERWORTH_Q, 0.0);
+                    }
+                    processed_l[idx] = state.speaker_hp_l.iter_mut().fold(processed_l[idx], |sample, filter| filter.process(sample));
+                    processed_r[idx] = state.speaker_hp_r.iter_mut().fold(processed_r[idx], |sample, filter| filter.process(sample));
+                }
+            }
+
+            // Output gain and assign back so we can output our processed sounds. Right goes first
+            // so a single channel (mono or center) keeps the left lane
+            for idx in 0..block_len {
+                channels[lane_r][idx] = processed_r[idx] * output_gain[idx];
+                channels[lane_l][idx] = processed_l[idx] * output_gain[idx];
+            }
+
+            // The aux layers get the same wet and output gain as the main output
+            if pair_idx == FRONT_PAIR {
+                for layer in self.sub_layer.iter_mut().chain(self.harmonic_layer.iter_mut()) {
+                    for idx in 0..block_len {
+                        layer[idx] *= dry_wet[idx] * output_gain[idx];
+                    }
+                }
+            }
+        }
+
+        // The sub by itself on top of whatever the LFE channel already had, through the same
+        // algorithm, hardness, DC blocking and output gain as the sub in the mains
+        if has_lfe {
+            for idx in 0..block_len {
+                let mut sub_sample: f32 = 0.0;
+                if sub_to_lfe {
+                    let settings: &HarmonicSettings = &harmonic_settings[idx];
+                    sub_sample = sub_through_algorithm(current_algorithm, sub_bump[idx], sub_gain[idx], settings);
+                    if fading {
+                        let old: f32 = sub_through_algorithm(self.fade_from_algorithm, sub_bump[idx], sub_gain[idx], settings);
+                        sub_sample = old + (sub_sample - old) * fade_amount[idx];
+                    }
+                }
+                sub_sample = dc_block(sub_sample, &mut self.prev_lfe_in, &mut self.prev_lfe_out);
+
+                let lfe = &mut channels[LFE_CHANNEL][idx];
+                *lfe += sub_sample * dry_wet[idx] * output_gain[idx];
+            }
+        }
+
+        // calculations that are only displayed on the GUI while the GUI is open
+        if self.params.editor_state.is_open() {
+            let Some((front_l, front_r)) = pairs[FRONT_PAIR] else { return; };
+            for idx in 0..block_len {
+                let out_l: f32 = channels[front_l][idx];
+                let out_r: f32 = channels[front_r][idx];
+                self.scope_in.push((front_in_l[idx] + front_in_r[idx]) * 0.5);
+                self.scope_out.push((out_l + out_r) * 0.5);
+
+                // Input gain meter
+                let in_amplitude: f32 = ((front_in_l[idx] + front_in_r[idx]) / 2.0).abs();
+                let current_in_meter = self.in_meter.load(std::sync::atomic::Ordering::Relaxed);
+                let new_in_meter = if in_amplitude > current_in_meter {in_amplitude} else {current_in_meter * self.out_meter_decay_weight + in_amplitude * (1.0 - self.out_meter_decay_weight)};
+                self.in_meter.store(new_in_meter, std::sync::atomic::Ordering::Relaxed);
+
+                // Output gain meter
+                let out_amplitude: f32 = ((out_l + out_r) / 2.0).abs();
+                let current_out_meter = self.out_meter.load(std::sync::atomic::Ordering::Relaxed);
+                let new_out_meter = if out_amplitude > current_out_meter {out_amplitude} else {current_out_meter * self.out_meter_decay_weight + out_amplitude * (1.0 - self.out_meter_decay_weight)};
+                self.out_meter.store(new_out_meter, std::sync::atomic::Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// The original Subhoofer sub generator - one sample of the mono sub from the summed input.
+    /// `gate_input` is the same sum before the lookahead delay so only the gate hears ahead.
+    /// Public for the benches like `process_block`
+    pub fn process_sub(&mut self, center: f32, gate_input: f32, settings: &SubSettings) -> f32 {
+        let SubSettings {
+            hoof_hardness,
+            sub_gain,
+            sub_drive,
+            divider_gains,
+            sub_shape,
+            sub_phase,
+            sub_invert,
+            auto_align,
+            min_freq,
+            max_freq,
+            overall_scale,
+        } = *settings;
+        let sample_rate: f32 = overall_scale * 44100.0;
+
+        // I picked this
+        let mut fake_random: f32 = 0.83;
+        let inv_fake_random: f32 = 1.0 - fake_random;
+        fake_random /= 2.0;
+
+        let mut sub_bump: f32;
+
+        // Sub voicing variables
+        let sub_headbump_freq: f32 = (((hoof_hardness) * 0.1) + 0.02) / overall_scale;
+        self.sub_iir = sub_headbump_freq / 44.1;
+
+        // Sub drive samples
+        // self.lp is our center signal
+        self.lp = center / 4096.0;
+        self.iir_drive_sample_a = (self.iir_drive_sample_a * (1.0 - sub_headbump_freq)) + (self.lp * sub_headbump_freq);
+        self.lp = self.iir_drive_sample_a;
+        self.iir_drive_sample_b = (self.iir_drive_sample_b * (1.0 - sub_headbump_freq)) + (self.lp * sub_headbump_freq);
+        self.lp = self.iir_drive_sample_b;
+        // Gate from airwindows
+        self.osc_gate += (self.lp * 10.0).abs();
+        self.osc_gate -= 0.001;
+        if self.osc_gate > 1.0 {self.osc_gate = 1.0;}
+        if self.osc_gate < 0.0 {self.osc_gate = 0.0;}
+        //got a value that only goes down low when there's silence or near silence on input.
+        //Whatever is left under the clamp goes to zero so it can't sit there as DC
+        let clamp: f32 = (1.0 - self.osc_gate) * 0.00001;
+        // The user's gate holds the dividers and fades the sub when the input is under its threshold
+        let gate_gain: f32 = self.sub_gate.process(gate_input);
+        let gate_open: bool = self.sub_gate.is_open();
+        // A note after the sub went silent starts the dividers and the sub's filters over, so the
+        // first upward crossing always starts the sub on the same polarity. With lookahead the gate
+        // hears the note coming and this happens before it reaches the sub
+        if self.sub_gate.is_onset() {
+            self.sub_octave = false;
+            self.sub_octave_2 = false;
+            self.sub_fifth = false;
+            self.fifth_half_cycles = 0;
+            self.was_negative = true;
+            self.iir_sub_bump_a = 0.0;
+            self.iir_sub_bump_b = 0.0;
+            self.iir_sub_bump_c = 0.0;
+            self.sub_shaper.reset();
+            self.sub_aligner.reset();
+        }
+        // Figure out our zero crossing
+        self.samples_since_crossing = self.samples_since_crossing.saturating_add(1);
+        // A rest or a closed gate isn't a period of the input, the next note's first crossing only
+        // starts counting and the last period is kept until then
+        if !gate_open || self.samples_since_crossing as f32 > sample_rate / SUB_RANGE_MIN_HZ {
+            self.period_counting = false;
+        }
+        if self.lp > 0.0
+        {
+            // We are on top of zero crossing
+            if self.was_negative
+            {
+                // Time between crossings is the input period for the sub shape's tracking filter
+                if self.period_counting {
+                    self.input_period = self.samples_since_crossing;
+                }
+                self.samples_since_crossing = 0;
+                self.period_counting = true;
+                // A closed gate holds the dividers where they are
+                if gate_open {
+                    self.sub_octave = !self.sub_octave;
+                    // The second flip-flop runs off the first for two octaves down
+                    if self.sub_octave { self.sub_octave_2 = !self.sub_octave_2; }
+                    self.fifth_half_cycles += 1;
+                }
+                self.was_negative = false;
+            }
+        }
+        else {
+            // On bottom of zero crossing
+            if !self.was_negative && gate_open { self.fifth_half_cycles += 1; }
+            self.was_negative = true;
+        }
+        // Dividing by 3 needs half cycles so the flip still lands on a zero crossing
+        if self.fifth_half_cycles >= 3
+        {
+            self.fifth_half_cycles = 0;
+            self.sub_fifth = !self.sub_fifth;
+        }
+        self.iir_sample_a = (self.iir_sample_a * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_a;
+			self.iir_sample_b = (self.iir_sample_b * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_b;
+			self.iir_sample_c = (self.iir_sample_c * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_c;
+			self.iir_sample_d = (self.iir_sample_d * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_d;
+			self.iir_sample_e = (self.iir_sample_e * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_e;
+			self.iir_sample_f = (self.iir_sample_f * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_f;
+			self.iir_sample_g = (self.iir_sample_g * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_g;
+			self.iir_sample_h = (self.iir_sample_h * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_h;
+			self.iir_sample_i = (self.iir_sample_i * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_i;
+			self.iir_sample_j = (self.iir_sample_j * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_j;
+			self.iir_sample_k = (self.iir_sample_k * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_k;
+			self.iir_sample_l = (self.iir_sample_l * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_l;
+			self.iir_sample_m = (self.iir_sample_m * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_m;
+			self.iir_sample_n = (self.iir_sample_n * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_n;
+			self.iir_sample_o = (self.iir_sample_o * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_o;
+			self.iir_sample_p = (self.iir_sample_p * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_p;
+			self.iir_sample_q = (self.iir_sample_q * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_q;
+			self.iir_sample_r = (self.iir_sample_r * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_r;
+			self.iir_sample_s = (self.iir_sample_s * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_s;
+			self.iir_sample_t = (self.iir_sample_t * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_t;
+			self.iir_sample_u = (self.iir_sample_u * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_u;
+			self.iir_sample_v = (self.iir_sample_v * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_v;
+        let mut head_bump: f32 = self.lp;
+
+        // Regain some volume now that we have sampled
+        head_bump = head_bump * 256.0;
+
+        // Calculate drive samples based off the processing so far
+        self.iir_sample_w = (self.iir_sample_w * (1.0 - self.sub_iir)) + (head_bump * self.sub_iir);    head_bump -= self.iir_sample_w;
+			self.iir_sample_x = (self.iir_sample_x * (1.0 - self.sub_iir)) + (head_bump * self.sub_iir);    head_bump -= self.iir_sample_x;
+
+        // Create SubBump sample from our head bump to modify further
+			sub_bump = head_bump;
+			self.iir_sample_y = (self.iir_sample_y * (1.0 - self.sub_iir)) + (sub_bump * self.sub_iir);    sub_bump -= self.iir_sample_y;
+
+        // Calculate sub drive samples based off what we've done so far		
+        self.iir_drive_sample_c = (self.iir_drive_sample_c * (1.0 - sub_headbump_freq)) + (sub_bump * sub_headbump_freq);   sub_bump = self.iir_drive_sample_c;
+        self.iir_drive_sample_d = (self.iir_drive_sample_d * (1.0 - sub_headbump_freq)) + (sub_bump * sub_headbump_freq);   sub_bump = self.iir_drive_sample_d;
+
+        // Flip the bump sample per divider for half-freq, quarter-freq and third-freq
+        sub_bump = sub_bump.abs();
+        let octave_1: f32 = if !self.sub_octave { -sub_bump } else { sub_bump };
+        let octave_2: f32 = if !self.sub_octave_2 { -sub_bump } else { sub_bump };
+        let fifth: f32 = if !self.sub_fifth { -sub_bump } else { sub_bump };
+        sub_bump = octave_1 * divider_gains[0] + octave_2 * divider_gains[1] + fifth * divider_gains[2];
+        // Note the randD/invrandD is what is flipping from positive to negative here
+			// This means bflip = 1 A gets inverted
+			// This means bflip = 2 B gets inverted
+			// This means bflip = 3 C gets inverted
+			// This creates a lower octave using  multiplication depending on sample
+        match self.bass_flip_counter
+        {
+            1 => {
+                self.iir_sub_bump_a += sub_bump * sub_gain;
+                self.iir_sub_bump_a -= self.iir_sub_bump_a * self.iir_sub_bump_a * self.iir_sub_bump_a * sub_headbump_freq;
+                self.iir_sub_bump_a = (inv_fake_random * self.iir_sub_bump_a) + (fake_random * self.iir_sub_bump_b) + (fake_random * self.iir_sub_bump_c);
+                if self.iir_sub_bump_a.abs() <= clamp { self.iir_sub_bump_a = 0.0; }
+                if self.iir_sub_bump_a > 0.0 { self.iir_sub_bump_a -= clamp; }
+                if self.iir_sub_bump_a < 0.0 { self.iir_sub_bump_a += clamp; }
+                sub_bump = self.iir_sub_bump_a;
+            }
+            2 => {
+                self.iir_sub_bump_b += sub_bump * sub_gain;
+                self.iir_sub_bump_b -= self.iir_sub_bump_b * self.iir_sub_bump_b * self.iir_sub_bump_b * sub_headbump_freq;
+                self.iir_sub_bump_b = (fake_random * self.iir_sub_bump_a) + (inv_fake_random * self.iir_sub_bump_b) + (fake_random * self.iir_sub_bump_c);
+                if self.iir_sub_bump_b.abs() <= clamp { self.iir_sub_bump_b = 0.0; }
+                if self.iir_sub_bump_b > 0.0 { self.iir_sub_bump_b -= clamp; }
+                if self.iir_sub_bump_b < 0.0 { self.iir_sub_bump_b += clamp; }
+                sub_bump = self.iir_sub_bump_b;
+            }
+            3 => {
+                self.iir_sub_bump_c += sub_bump * sub_gain;
+                self.iir_sub_bump_c -= self.iir_sub_bump_c * self.iir_sub_bump_c * self.iir_sub_bump_c * sub_headbump_freq;
+                self.iir_sub_bump_c = (fake_random * self.iir_sub_bump_a) + (fake_random * self.iir_sub_bump_b) + (inv_fake_random * self.iir_sub_bump_c);
+                if self.iir_sub_bump_c.abs() <= clamp { self.iir_sub_bump_c = 0.0; }
+                if self.iir_sub_bump_c > 0.0 { self.iir_sub_bump_c -= clamp; }
+                if self.iir_sub_bump_c < 0.0 { self.iir_sub_bump_c += clamp; }
+                sub_bump = self.iir_sub_bump_c;
+            }
+            _ => unreachable!()
+        }
+        // Resample to reduce the sub bump further
+        self.iir_sample_z = (self.iir_sample_z * (1.0 - sub_headbump_freq)) + (sub_bump * sub_headbump_freq);
+        sub_bump = self.iir_sample_z;
+        self.iir_drive_sample_e = (self.iir_drive_sample_e * (1.0 - self.sub_iir)) + (sub_bump * self.sub_iir);
+        sub_bump = self.iir_drive_sample_e;
+        self.iir_drive_sample_f = (self.iir_drive_sample_f * (1.0 - self.sub_iir)) + (sub_bump * self.sub_iir);
+        sub_bump = self.iir_drive_sample_f;
+
+        // Shape the sub, tracking the highest divider that's in use so none of them get filtered away
+        let (divide_by, divider_flip): (f32, bool) =
+            if divider_gains[0] > 0.0 { (2.0, self.sub_octave) }
+            else if divider_gains[2] > 0.0 { (3.0, self.sub_fifth) }
+            else { (4.0, self.sub_octave_2) };
+        let sub_freq: f32 = 1.0 / (self.input_period.max(1) as f32 * divide_by);
+        sub_bump = self.sub_shaper.process(sub_bump, sub_freq, sub_shape);
+
+        // Calculate our final sub drive
+        if sub_drive > 0.0
+        {
+            sub_bump += tape_saturation(sub_bump, sub_drive);
+        }
+
+        // Line the sub up with the dry bass, it fades out to the untouched sub when there's nothing to do
+        let divider_sign: f32 = if divider_flip { 1.0 } else { -1.0 };
+        sub_bump = self.sub_aligner.process(sub_bump, center, divider_sign, sub_freq, sample_rate, sub_phase, auto_align);
+        if sub_invert { sub_bump = -sub_bump; }
+        sub_bump *= gate_gain;
+
+        // Fade out for input notes outside the sub input range
+        let input_freq: f32 = sample_rate / self.input_period.max(1) as f32;
+        let range_target: f32 = sub_range_gain(input_freq, min_freq, max_freq);
+        self.sub_range_gain += (range_target - self.sub_range_gain) / (SUB_RANGE_FADE_MS * 0.001 * sample_rate);
+        sub_bump *= self.sub_range_gain;
+
+        // Increment/change the bass_flip_counter
+        self.bass_flip_counter += 1;
+        self.bass_flip_counter = 
+            if self.bass_flip_counter < 1 || self.bass_flip_counter > 3 { 1 } 
+            else { self.bass_flip_counter };
+
+        sub_bump
+    }
+}
+
+impl Plugin for Subhoofer {
+    const NAME: &'static str = "Subhoofer";
+    const VENDOR: &'static str = "Ardura";
+    const URL: &'static str = "https://github.com/ardura";
+    const EMAIL: &'static str = "azviscarra@gmail.com";
+
+    const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+    // Stereo and mono with aux outputs for the sub and harmonics, and the 5.1/7.1 surround layouts
+    const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(2),
+            main_output_channels: NonZeroU32::new(2),
+            aux_input_ports: &[new_nonzero_u32(2)],
+            aux_output_ports: &[new_nonzero_u32(2), new_nonzero_u32(2)],
+            names: PortNames {aux_inputs: &["Sidechain"], aux_outputs: &["Sub", "Harmonics"], ..PortNames::const_default()},
+            ..AudioIOLayout::const_default()
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(1),
+            main_output_channels: NonZeroU32::new(1),
+            aux_input_ports: &[new_nonzero_u32(1)],
+            aux_output_ports: &[new_nonzero_u32(1), new_nonzero_u32(1)],
+            names: PortNames {aux_inputs: &["Sidechain"], aux_outputs: &["Sub", "Harmonics"], ..PortNames::const_default()},
+            ..AudioIOLayout::const_default()
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(6),
+            main_output_channels: NonZeroU32::new(6),
+            aux_input_ports: &[new_nonzero_u32(2)],
+            names: PortNames {layout: Some("5.1"), aux_inputs: &["Sidechain"], ..PortNames::const_default()},
+            ..AudioIOLayout::const_default()
+        },
+        AudioIOLayout {
+            main_input_channels: NonZeroU32::new(8),
+            main_output_channels: NonZeroU32::new(8),
+            aux_input_ports: &[new_nonzero_u32(2)],
+            names: PortNames {layout: Some("7.1"), aux_inputs: &["Sidechain"], ..PortNames::const_default()},
+            ..AudioIOLayout::const_default()
+        },
+    ];
+
+    const SAMPLE_ACCURATE_AUTOMATION: bool = true;
+
+    type SysExMessage = ();
+    type BackgroundTask = ();
 
     fn params(&self) -> Arc<dyn Params> {
         self.params.clone()
@@ -461,36 +2392,82 @@ impl Plugin for Subhoofer {
         let params = self.params.clone();
         let in_meter = self.in_meter.clone();
         let out_meter = self.out_meter.clone();
+        let dynamics_gain = self.dynamics_gain.clone();
+        let gate_open = self.gate_open.clone();
+        let scope_in = self.scope_in.clone();
+        let scope_out = self.scope_out.clone();
+        // User themes are read from disk once when the editor opens
+        let themes = Theme::all();
         create_egui_editor(
             self.params.editor_state.clone(),
-            (),
+            EditorPage::Main,
             |_, _| {},
-            move |egui_ctx, setter, _state| {
-                egui::CentralPanel::default()
-                    .show(egui_ctx, |ui| {
+            move |egui_ctx, setter, page| {
+                ResizableWindow::new("subhoofer-window")
+                    .min_size(egui::vec2(WIDTH as f32 * ZOOM_PRESETS[0], HEIGHT as f32 * ZOOM_PRESETS[0]))
+                    .show(egui_ctx, params.editor_state.as_ref(), |ui| {
+                        let theme = Theme::find(&themes, &params.theme.read().unwrap());
+
                         // Change colors - there's probably a better way to do this
-                        let style_var = ui.style_mut().clone();
+                        let mut style_var = ui.style_mut().clone();
+                        style_var.visuals.widgets.inactive.bg_fill = theme.panel;
+                        style_var.visuals.widgets.inactive.weak_bg_fill = theme.panel;
+                        style_var.visuals.selection.bg_fill = theme.primary;
+
+                        // Everything below is sized off this so the layout follows the window
+                        let window_rect = ui.ctx().screen_rect();
+                        let scale = (window_rect.width() / WIDTH as f32).min(window_rect.height() / HEIGHT as f32);
 
                         // Trying to draw background as rect
-                        ui.painter().rect_filled(
-                            Rect::from_x_y_ranges(
-                                RangeInclusive::new(0.0, WIDTH as f32), 
-                                RangeInclusive::new(0.0, HEIGHT as f32)), 
-                            Rounding::from(16.0), NAVY_BLUE);
+                        ui.painter().rect_filled(window_rect, Rounding::from(16.0 * scale), theme.background);
 
                         // Screws for that vintage look
-                        let screw_space = 10.0;
-                        ui.painter().circle_filled(Pos2::new(screw_space,screw_space), 4.0, Color32::DARK_GRAY);
-                        ui.painter().circle_filled(Pos2::new(screw_space,HEIGHT as f32 - screw_space), 4.0, Color32::DARK_GRAY);
-                        ui.painter().circle_filled(Pos2::new(WIDTH as f32 - screw_space,screw_space), 4.0, Color32::DARK_GRAY);
-                        ui.painter().circle_filled(Pos2::new(WIDTH as f32 - screw_space,HEIGHT as f32 - screw_space), 4.0, Color32::DARK_GRAY);
+                        let screw_space = 10.0 * scale;
+                        let screw_size = 4.0 * scale;
+                        ui.painter().circle_filled(window_rect.left_top() + egui::vec2(screw_space, screw_space), screw_size, theme.screws);
+                        ui.painter().circle_filled(window_rect.left_bottom() + egui::vec2(screw_space, -screw_space), screw_size, theme.screws);
+                        ui.painter().circle_filled(window_rect.right_top() + egui::vec2(-screw_space, screw_space), screw_size, theme.screws);
+                        ui.painter().circle_filled(window_rect.right_bottom() + egui::vec2(-screw_space, -screw_space), screw_size, theme.screws);
 
                         ui.set_style(style_var);
 
                         // GUI Structure
                         ui.vertical(|ui| {
-                            // Spacing :)
-                            ui.label(RichText::new("    Subhoofer").font(FontId::proportional(14.0)).color(BEIGE)).on_hover_text("by Ardura!");
+                            ui.horizontal(|ui| {
+                                // Spacing :)
+                                ui.label(RichText::new("    Subhoofer").font(FontId::proportional(14.0 * scale)).color(theme.primary)).on_hover_text("by Ardura!");
+
+                                ui.add_space((ui.available_width() - 200.0 * scale).max(0.0));
+                                egui::ComboBox::from_id_source("theme")
+                                    .width(112.0 * scale)
+                                    .selected_text(theme.name.clone())
+                                    .show_ui(ui, |ui| {
+                                        for option in themes.iter() {
+                                            if ui.selectable_label(option.name == theme.name, option.name.clone()).clicked() {
+                                                *params.theme.write().unwrap() = option.name.clone();
+                                            }
+                                        }
+                                    })
+                                    .response
+                                    .on_hover_text("GUI theme - add your own in Subhoofer/themes.toml in your config folder");
+
+                                // Zoom presets resize the window, dragging the corner works too
+                                egui::ComboBox::from_id_source("zoom")
+                                    .width(64.0 * scale)
+                                    .selected_text(format!("{:.0}%", scale * 100.0))
+                                    .show_ui(ui, |ui| {
+                                        for zoom in ZOOM_PRESETS {
+                                            if ui.selectable_label((scale - zoom).abs() < 0.01, format!("{:.0}%", zoom * 100.0)).clicked() {
+                                                params.editor_state.set_requested_size((
+                                                    (WIDTH as f32 * zoom).round() as u32,
+                                                    (HEIGHT as f32 * zoom).round() as u32,
+                                                ));
+                                            }
+                                        }
+                                    })
+                                    .response
+                                    .on_hover_text("Editor size");
+                            });
 
                             // Peak Meters
                             let in_meter = util::gain_to_db(in_meter.load(std::sync::atomic::Ordering::Relaxed));
@@ -501,10 +2478,11 @@ impl Plugin for Subhoofer {
                             };
                             let in_meter_normalized = (in_meter + 60.0) / 60.0;
                             ui.allocate_space(egui::Vec2::splat(2.0));
-                            let mut in_meter_obj = db_meter::DBMeter::new(in_meter_normalized).text(in_meter_text);
-                            in_meter_obj.set_background_color(TEAL);
-                            in_meter_obj.set_bar_color(BEIGE);
-                            in_meter_obj.set_border_color(Color32::BLACK);
+                            let mut in_meter_obj = db_meter::DBMeter::new(in_meter_normalized)
+                                .text(in_meter_text)
+                                .desired_height(18.0 * scale)
+                                .text_size(10.0 * scale);
+                            in_meter_obj.set_theme(&theme);
                             ui.add(in_meter_obj);
 
                             let out_meter = util::gain_to_db(out_meter.load(std::sync::atomic::Ordering::Relaxed));
@@ -515,133 +2493,546 @@ impl Plugin for Subhoofer {
                             };
                             let out_meter_normalized = (out_meter + 60.0) / 60.0;
                             ui.allocate_space(egui::Vec2::splat(2.0));
-                            let mut out_meter_obj = db_meter::DBMeter::new(out_meter_normalized).text(out_meter_text);
-                            out_meter_obj.set_background_color(TEAL);
-                            out_meter_obj.set_bar_color(BEIGE);
-                            out_meter_obj.set_border_color(Color32::BLACK);
+                            let mut out_meter_obj = db_meter::DBMeter::new(out_meter_normalized)
+                                .text(out_meter_text)
+                                .desired_height(18.0 * scale)
+                                .text_size(10.0 * scale);
+                            out_meter_obj.set_theme(&theme);
                             ui.add(out_meter_obj);
 
+                            // Page tabs
                             ui.horizontal(|ui| {
-                                let knob_size = 42.0;
-                                let text_size = 12.0;
-                                ui.vertical(|ui| {
-                                    let gain_knob = ui_knob::ArcKnob::for_param(
-                                        &params.free_gain, 
-                                        setter, 
-                                        knob_size, 
-                                        ui_knob::KnobLayout::Horizonal)
-                                            .preset_style(ui_knob::KnobStyle::Preset1)
-                                            .set_fill_color(TEAL)
-                                            .set_line_color(BEIGE)
-                                            .set_text_size(text_size)
-                                            .set_hover_text("Input gain into Subhoofer".to_string());
-                                    ui.add(gain_knob);
-
-                                    let output_knob = ui_knob::ArcKnob::for_param(
-                                        &params.output_gain, 
-                                        setter, 
-                                        knob_size, 
-                                        ui_knob::KnobLayout::Horizonal)
-                                            .preset_style(ui_knob::KnobStyle::Preset1)
-                                            .set_fill_color(TEAL)
-                                            .set_line_color(BEIGE)
-                                            .set_text_size(text_size)
-                                            .set_hover_text("Output gain from Subhoofer".to_string());
-                                    ui.add(output_knob);
-
-                                    let algorithm_knob = ui_knob::ArcKnob::for_param(
-                                        &params.h_algorithm, 
-                                        setter, 
-                                        knob_size, 
-                                        ui_knob::KnobLayout::Horizonal)
-                                            .preset_style(ui_knob::KnobStyle::Preset1)
-                                            .set_fill_color(TEAL)
-                                            .set_line_color(BEIGE)
-                                            .set_text_size(text_size)
-                                            .set_hover_text("The saturation/harmonic algorithm used".to_string());
-                                    ui.add(algorithm_knob);
+                                ui.add_space(16.0 * scale);
+                                for (option, name) in [(EditorPage::Main, "Main"), (EditorPage::Tone, "Tone"), (EditorPage::Sub, "Sub"), (EditorPage::Modulation, "Mod"), (EditorPage::Visualizer, "Visualizer"), (EditorPage::Settings, "Settings")] {
+                                    if ui.selectable_label(*page == option, RichText::new(name).size(12.0 * scale).color(theme.primary)).clicked() {
+                                        *page = option;
+                                    }
+                                }
+                            });
+
+                            if *page == EditorPage::Main {
+                                ui.horizontal(|ui| {
+                                    let knob_size = 42.0 * scale;
+                                    let text_size = 12.0 * scale;
+                                    ui.vertical(|ui| {
+                                        let gain_knob = ui_knob::ArcKnob::for_param(
+                                            &params.free_gain, 
+                                            setter, 
+                                            knob_size, 
+                                            ui_knob::KnobLayout::Horizonal)
+                                                .preset_style(ui_knob::KnobStyle::Preset1)
+                                                .set_theme(&theme)
+                                                .set_text_size(text_size)
+                                                .set_hover_text("Input gain into Subhoofer".to_string());
+                                        ui.add(gain_knob);
+
+                                        let output_knob = ui_knob::ArcKnob::for_param(
+                                            &params.output_gain, 
+                                            setter, 
+                                            knob_size, 
+                                            ui_knob::KnobLayout::Horizonal)
+                                                .preset_style(ui_knob::KnobStyle::Preset1)
+                                                .set_theme(&theme)
+                                                .set_text_size(text_size)
+                                                .set_hover_text("Output gain from Subhoofer".to_string());
+                                        ui.add(output_knob);
+
+                                        let algorithm_knob = ui_knob::ArcKnob::for_param(
+                                            &params.h_algorithm, 
+                                            setter, 
+                                            knob_size, 
+                                            ui_knob::KnobLayout::Horizonal)
+                                                .preset_style(ui_knob::KnobStyle::Preset1)
+                                                .set_theme(&theme)
+                                                .set_text_size(text_size)
+                                                .set_hover_text("The saturation/harmonic algorithm used".to_string());
+                                        ui.add(algorithm_knob);
+                                
+                                        let dry_wet_knob = ui_knob::ArcKnob::for_param(
+                                            &params.dry_wet, 
+                                            setter, 
+                                            knob_size, 
+                                            ui_knob::KnobLayout::Horizonal)
+                                                .preset_style(ui_knob::KnobStyle::Preset1)
+                                                .set_theme(&theme)
+                                                .set_text_size(text_size)
+                                                .set_hover_text("The blend of unprocessed/processed signal".to_string());
+                                        ui.add(dry_wet_knob);
+                                    });
+
+                                    ui.vertical(|ui| {
+                                        let hardness_knob = ui_knob::ArcKnob::for_param(
+                                            &params.hoof_hardness, 
+                                            setter, 
+                                            knob_size, 
+                                            ui_knob::KnobLayout::Horizonal)
+                                                .preset_style(ui_knob::KnobStyle::Preset1)
+                                                .set_theme(&theme)
+                                                .set_line_color(theme.secondary)
+                                                .set_text_size(text_size)
+                                                .set_hover_text("The amount of saturation Subhoofer uses".to_string());
+                                        ui.add(hardness_knob);
+
+                                        let harmonics_knob = ui_knob::ArcKnob::for_param(
+                                            &params.harmonics, 
+                                            setter, 
+                                            knob_size, 
+                                            ui_knob::KnobLayout::Horizonal)
+                                                .preset_style(ui_knob::KnobStyle::Preset1)
+                                                .set_theme(&theme)
+                                                .set_line_color(theme.secondary)
+                                                .set_text_size(text_size)
+                                                .set_hover_text("The strength of harmonics added to signal".to_string());
+                                        ui.add(harmonics_knob);
+
+                                        let sub_gain_knob = ui_knob::ArcKnob::for_param(
+                                            &params.sub_gain, 
+                                            setter, 
+                                            knob_size, 
+                                            ui_knob::KnobLayout::Horizonal)
+                                                .preset_style(ui_knob::KnobStyle::Preset1)
+                                                .set_theme(&theme)
+                                                .set_line_color(theme.secondary)
+                                                .set_text_size(text_size)
+                                                .set_hover_text("Gain for the sub layer".to_string());
+                                        ui.add(sub_gain_knob);
                                 
-                                    let dry_wet_knob = ui_knob::ArcKnob::for_param(
-                                        &params.dry_wet, 
-                                        setter, 
-                                        knob_size, 
-                                        ui_knob::KnobLayout::Horizonal)
-                                            .preset_style(ui_knob::KnobStyle::Preset1)
-                                            .set_fill_color(TEAL)
-                                            .set_line_color(BEIGE)
-                                            .set_text_size(text_size)
-                                            .set_hover_text("The blend of unprocessed/processed signal".to_string());
-                                    ui.add(dry_wet_knob);
+                                        let sub_drive_knob = ui_knob::ArcKnob::for_param(
+                                            &params.sub_drive, 
+                                            setter, 
+                                            knob_size, 
+                                            ui_knob::KnobLayout::Horizonal)
+                                                .preset_style(ui_knob::KnobStyle::Preset1)
+                                                .set_theme(&theme)
+                                                .set_line_color(theme.secondary)
+                                                .set_text_size(text_size)
+                                                .set_hover_text("Drive into the sub algorithm".to_string());
+                                        ui.add(sub_drive_knob);
+                                    });
+                                });
+                                //sliders
+                                ui.horizontal(|ui|{
+                                    ui.add_space(16.0 * scale);
+                                    ui.vertical(|ui| {
+                                        ui.add(widgets::ParamSlider::for_param(&params.custom_harmonics1, setter).with_width(170.0 * scale))
+                                            .on_hover_text_at_pointer("Add harmonics when using \"Custom\" Algorithm
+    Double-click to reset");
+                                        ui.add(widgets::ParamSlider::for_param(&params.custom_harmonics2, setter).with_width(170.0 * scale))
+                                            .on_hover_text_at_pointer("Add harmonics when using \"Custom\" Algorithm
+    Double-click to reset");
+                                        ui.add(widgets::ParamSlider::for_param(&params.custom_harmonics3, setter).with_width(170.0 * scale))
+                                            .on_hover_text_at_pointer("Add harmonics when using \"Custom\" Algorithm
+    Double-click to reset");
+                                        ui.add(widgets::ParamSlider::for_param(&params.custom_harmonics4, setter).with_width(170.0 * scale))
+                                            .on_hover_text_at_pointer("Add harmonics when using \"Custom\" Algorithm
+    Double-click to reset");
+                                    });
+
+                                    // Small speaker mode
+                                    ui.vertical(|ui| {
+                                        let mut small_speaker = params.small_speaker.value();
+                                        if ui.checkbox(&mut small_speaker, RichText::new("Small Speaker").size(12.0 * scale).color(theme.primary))
+                                            .on_hover_text("Remove the bass below the speaker cutoff and keep its harmonics")
+                                            .changed()
+                                        {
+                                            setter.begin_set_parameter(&params.small_speaker);
+                                            setter.set_parameter(&params.small_speaker, small_speaker);
+                                            setter.end_set_parameter(&params.small_speaker);
+                                        }
+
+                                        let cutoff_knob = ui_knob::ArcKnob::for_param(
+                                            &params.speaker_cutoff, 
+                                            setter, 
+                                            24.0 * scale, 
+                                            ui_knob::KnobLayout::Horizonal)
+                                                .preset_style(ui_knob::KnobStyle::Preset1)
+                                                .set_theme(&theme)
+                                                .set_text_size(10.0 * scale)
+                                                .set_hover_text("The lowest frequency the target speaker can play".to_string());
+                                        ui.add(cutoff_knob);
+                                    });
                                 });
 
-                                ui.vertical(|ui| {
-                                    let hardness_knob = ui_knob::ArcKnob::for_param(
-                                        &params.hoof_hardness, 
-                                        setter, 
-                                        knob_size, 
-                                        ui_knob::KnobLayout::Horizonal)
-                                            .preset_style(ui_knob::KnobStyle::Preset1)
-                                            .set_fill_color(TEAL)
-                                            .set_line_color(LIGHT_GREY)
-                                            .set_text_size(text_size)
-                                            .set_hover_text("The amount of saturation Subhoofer uses".to_string());
-                                    ui.add(hardness_knob);
-
-                                    let harmonics_knob = ui_knob::ArcKnob::for_param(
-                                        &params.harmonics, 
-                                        setter, 
-                                        knob_size, 
-                                        ui_knob::KnobLayout::Horizonal)
-                                            .preset_style(ui_knob::KnobStyle::Preset1)
-                                            .set_fill_color(TEAL)
-                                            .set_line_color(LIGHT_GREY)
-                                            .set_text_size(text_size)
-                                            .set_hover_text("The strength of harmonics added to signal".to_string());
-                                    ui.add(harmonics_knob);
-
-                                    let sub_gain_knob = ui_knob::ArcKnob::for_param(
-                                        &params.sub_gain, 
-                                        setter, 
-                                        knob_size, 
-                                        ui_knob::KnobLayout::Horizonal)
-                                            .preset_style(ui_knob::KnobStyle::Preset1)
-                                            .set_fill_color(TEAL)
-                                            .set_line_color(LIGHT_GREY)
-                                            .set_text_size(text_size)
-                                            .set_hover_text("Gain for the sub layer".to_string());
-                                    ui.add(sub_gain_knob);
-                                
-                                    let sub_drive_knob = ui_knob::ArcKnob::for_param(
-                                        &params.sub_drive, 
-                                        setter, 
-                                        knob_size, 
-                                        ui_knob::KnobLayout::Horizonal)
-                                            .preset_style(ui_knob::KnobStyle::Preset1)
-                                            .set_fill_color(TEAL)
-                                            .set_line_color(LIGHT_GREY)
-                                            .set_text_size(text_size)
-                                            .set_hover_text("Drive into the sub algorithm".to_string());
-                                    ui.add(sub_drive_knob);
+                                // Drawbars for the Chebyshev algorithm
+                                ui.horizontal(|ui|{
+                                    ui.add_space(16.0 * scale);
+                                    let drawbars = harmonic_drawbars::HarmonicDrawbars::for_params(
+                                        params.drawbars.iter().map(|drawbar| &drawbar.gain).collect(),
+                                        params.drawbars.iter().map(|drawbar| &drawbar.invert).collect(),
+                                        setter,
+                                        ChebyshevStack::FIRST_ORDER,
+                                        egui::vec2((WIDTH as f32 - 32.0) * scale, 96.0 * scale))
+                                            .set_theme(&theme)
+                                            .set_scale(scale);
+                                    ui.add(drawbars);
                                 });
-                            });
-                            //sliders
-                            ui.horizontal(|ui|{
-                                ui.add_space(16.0);
-                                ui.vertical(|ui| {
-                                    ui.add(widgets::ParamSlider::for_param(&params.custom_harmonics1, setter).with_width(170.0))
-                                        .on_hover_text_at_pointer("Add harmonics when using \"Custom\" Algorithm
-Double-click to reset");
-                                    ui.add(widgets::ParamSlider::for_param(&params.custom_harmonics2, setter).with_width(170.0))
-                                        .on_hover_text_at_pointer("Add harmonics when using \"Custom\" Algorithm
-Double-click to reset");
-                                    ui.add(widgets::ParamSlider::for_param(&params.custom_harmonics3, setter).with_width(170.0))
-                                        .on_hover_text_at_pointer("Add harmonics when using \"Custom\" Algorithm
-Double-click to reset");
-                                    ui.add(widgets::ParamSlider::for_param(&params.custom_harmonics4, setter).with_width(170.0))
-                                        .on_hover_text_at_pointer("Add harmonics when using \"Custom\" Algorithm
-Double-click to reset");
+                            }
+
+                            if *page == EditorPage::Tone {
+                                ui.horizontal(|ui| {
+                                    ui.add_space(16.0 * scale);
+                                    ui.vertical(|ui| {
+                                        let knob_size = 42.0 * scale;
+                                        let text_size = 12.0 * scale;
+                                        ui.label(RichText::new("Harmonic EQ").size(text_size).color(theme.primary));
+                                        for (param, hover) in [
+                                            (&params.harmonic_tilt, "Tilt the generated harmonics darker or brighter around 500 Hz"),
+                                            (&params.harmonic_high_pass, "Remove generated harmonics below this frequency"),
+                                            (&params.harmonic_low_pass, "Remove generated harmonics above this frequency to avoid fizz"),
+                                        ] {
+                                            let eq_knob = ui_knob::ArcKnob::for_param(
+                                                param, 
+                                                setter, 
+                                                knob_size, 
+                                                ui_knob::KnobLayout::Horizonal)
+                                                    .preset_style(ui_knob::KnobStyle::Preset1)
+                                                    .set_theme(&theme)
+                                                    .set_text_size(text_size)
+                                                    .set_hover_text(hover.to_string());
+                                            ui.add(eq_knob);
+                                        }
+
+                                        ui.add_space(8.0 * scale);
+                                        ui.label(RichText::new("Stereo").size(text_size).color(theme.primary));
+                                        ui.horizontal(|ui| {
+                                            ui.label(RichText::new("Stereo Mode").size(text_size).color(theme.secondary));
+                                            ui.add(widgets::ParamSlider::for_param(&params.stereo_mode, setter).with_width(120.0 * scale))
+                                                .on_hover_text_at_pointer("Generate harmonics from L/R, only the mid, only the side, or mid and side with their own depths");
+                                        });
+                                        for (param, hover) in [
+                                            (&params.mid_harmonics, "Depth of the harmonics from the mid in Mid+Side mode"),
+                                            (&params.side_harmonics, "Depth of the harmonics from the side in Mid+Side mode, to widen or narrow the harmonic layer"),
+                                        ] {
+                                            let depth_knob = ui_knob::ArcKnob::for_param(
+                                                param, 
+                                                setter, 
+                                                knob_size, 
+                                                ui_knob::KnobLayout::Horizonal)
+                                                    .preset_style(ui_knob::KnobStyle::Preset1)
+                                                    .set_theme(&theme)
+                                                    .set_text_size(text_size)
+                                                    .set_hover_text(hover.to_string());
+                                            ui.add(depth_knob);
+                                        }
+                                    });
+                                    ui.add_space(16.0 * scale);
+                                    ui.vertical(|ui| {
+                                        let knob_size = 42.0 * scale;
+                                        let text_size = 12.0 * scale;
+                                        ui.label(RichText::new("Dynamic Harmonics").size(text_size).color(theme.primary));
+                                        ui.add(widgets::ParamSlider::for_param(&params.dynamic_harmonics, setter).with_width(120.0 * scale))
+                                            .on_hover_text_at_pointer("Raise the harmonics as notes get quieter to keep decaying bass present, or as they get louder");
+                                        for (param, hover) in [
+                                            (&params.dynamics_threshold, "Input level where Dynamic Harmonics starts raising the harmonics"),
+                                            (&params.dynamics_ratio, "How much the harmonics rise for each dB past the threshold"),
+                                            (&params.dynamics_attack, "How quickly the envelope follows a rising input"),
+                                            (&params.dynamics_release, "How quickly the envelope follows a falling input"),
+                                        ] {
+                                            let dynamics_knob = ui_knob::ArcKnob::for_param(
+                                                param, 
+                                                setter, 
+                                                knob_size, 
+                                                ui_knob::KnobLayout::Horizonal)
+                                                    .preset_style(ui_knob::KnobStyle::Preset1)
+                                                    .set_theme(&theme)
+                                                    .set_text_size(text_size)
+                                                    .set_hover_text(hover.to_string());
+                                            ui.add(dynamics_knob);
+                                        }
+
+                                        // Gauge of the harmonics after Dynamic Harmonics, placed like the Harmonics knob
+                                        let gain = dynamics_gain.load(std::sync::atomic::Ordering::Relaxed);
+                                        let effective = params.harmonics.value() * gain;
+                                        ui.allocate_space(egui::Vec2::splat(2.0));
+                                        let mut gauge = db_meter::DBMeter::new(params.harmonics.preview_normalized(effective.min(1.0)))
+                                            .text(format!("{:.4}% Harmonics ({:+.1} dB)", effective * 100.0, util::gain_to_db(gain)))
+                                            .desired_width(140.0 * scale)
+                                            .desired_height(18.0 * scale)
+                                            .text_size(10.0 * scale);
+                                        gauge.set_theme(&theme);
+                                        ui.add(gauge);
+                                    });
                                 });
-                            });
+                            }
+
+                            if *page == EditorPage::Sub {
+                                ui.horizontal(|ui| {
+                                    ui.add_space(16.0 * scale);
+                                    ui.vertical(|ui| {
+                                        let knob_size = 42.0 * scale;
+                                        let text_size = 12.0 * scale;
+                                        ui.label(RichText::new("Sub Dividers and Shape").size(text_size).color(theme.primary));
+                                        for (param, hover) in [
+                                            (&params.sub_octave_1, "Level of the sub one octave below the input"),
+                                            (&params.sub_octave_2, "Level of the sub two octaves below the input"),
+                                            (&params.sub_fifth, "Level of the sub an octave and a fifth below the input (divide by 3)"),
+                                            (&params.sub_shape, "Morph the sub between sine, triangle, the original filtered square and saturated"),
+                                            (&params.sub_phase, "Delay the sub's phase so it lines up with the dry bass instead of cancelling it"),
+                                            (&params.sub_min_freq, "Input notes below this fade the sub out, to keep it from making mud under low notes"),
+                                            (&params.sub_max_freq, "Input notes above this fade the sub out, to avoid artifacts on higher notes"),
+                                        ] {
+                                            let divider_knob = ui_knob::ArcKnob::for_param(
+                                                param, 
+                                                setter, 
+                                                knob_size, 
+                                                ui_knob::KnobLayout::Horizonal)
+                                                    .preset_style(ui_knob::KnobStyle::Preset1)
+                                                    .set_theme(&theme)
+                                                    .set_line_color(theme.secondary)
+                                                    .set_text_size(text_size)
+                                                    .set_hover_text(hover.to_string());
+                                            ui.add(divider_knob);
+                                        }
+                                        for (param, name, hover) in [
+                                            (&params.sub_invert, "Sub Invert", "Flip the polarity of the sub"),
+                                            (&params.sub_auto_align, "Sub Auto Align", "Track how far the sub lags the dry bass and make it up on top of Sub Phase"),
+                                        ] {
+                                            let mut enabled = param.value();
+                                            if ui.checkbox(&mut enabled, RichText::new(name).size(text_size).color(theme.primary))
+                                                .on_hover_text(hover)
+                                                .changed()
+                                            {
+                                                setter.begin_set_parameter(param);
+                                                setter.set_parameter(param, enabled);
+                                                setter.end_set_parameter(param);
+                                            }
+                                        }
+                                    });
+                                    ui.add_space(16.0 * scale);
+                                    ui.vertical(|ui| {
+                                        let knob_size = 42.0 * scale;
+                                        let text_size = 12.0 * scale;
+                                        ui.label(RichText::new("Sub Ducking").size(text_size).color(theme.primary));
+                                        ui.add(widgets::ParamSlider::for_param(&params.sub_duck, setter).with_width(120.0 * scale))
+                                            .on_hover_text_at_pointer("Duck the sub on the host's beat grid or when the sidechain goes over the threshold. The dry signal isn't touched");
+                                        for (param, hover) in [
+                                            (&params.duck_every, "How often the beat grid ducks, 1/4 for four on the floor"),
+                                            (&params.duck_length, "How long the sub takes to come back after each duck"),
+                                        ] {
+                                            ui.add(widgets::ParamSlider::for_param(param, setter).with_width(120.0 * scale))
+                                                .on_hover_text_at_pointer(hover);
+                                        }
+                                        for (param, hover) in [
+                                            (&params.duck_depth, "How far the sub drops at the start of each duck"),
+                                            (&params.duck_shape, "0% brings the sub back in a straight line, 100% snaps it back early"),
+                                            (&params.duck_threshold, "Sidechain level that triggers a duck"),
+                                        ] {
+                                            let duck_knob = ui_knob::ArcKnob::for_param(
+                                                param, 
+                                                setter, 
+                                                knob_size, 
+                                                ui_knob::KnobLayout::Horizonal)
+                                                    .preset_style(ui_knob::KnobStyle::Preset1)
+                                                    .set_theme(&theme)
+                                                    .set_line_color(theme.secondary)
+                                                    .set_text_size(text_size)
+                                                    .set_hover_text(hover.to_string());
+                                            ui.add(duck_knob);
+                                        }
+
+                                        ui.add_space(8.0 * scale);
+                                        ui.horizontal(|ui| {
+                                            ui.label(RichText::new("Sub Gate").size(text_size).color(theme.primary));
+                                            // Lights up while the gate is letting the sub through
+                                            let (indicator, _) = ui.allocate_exact_size(egui::vec2(12.0 * scale, 12.0 * scale), egui::Sense::hover());
+                                            let lit = if gate_open.load(std::sync::atomic::Ordering::Relaxed) { theme.primary } else { theme.panel };
+                                            ui.painter().circle(indicator.center(), 5.0 * scale, lit, egui::Stroke::new(1.0, theme.outline));
+                                        });
+                                        for (param, hover) in [
+                                            (&params.gate_threshold, "Input level the sub needs before its dividers follow the input. All the way down never closes"),
+                                            (&params.gate_hysteresis, "How far under the threshold the input has to fall before the gate can close"),
+                                            (&params.gate_hold, "How long the gate stays open after the input falls away"),
+                                            (&params.gate_release, "How quickly the sub fades out once the gate closes"),
+                                        ] {
+                                            let gate_knob = ui_knob::ArcKnob::for_param(
+                                                param, 
+                                                setter, 
+                                                knob_size, 
+                                                ui_knob::KnobLayout::Horizonal)
+                                                    .preset_style(ui_knob::KnobStyle::Preset1)
+                                                    .set_theme(&theme)
+                                                    .set_line_color(theme.secondary)
+                                                    .set_text_size(text_size)
+                                                    .set_hover_text(hover.to_string());
+                                            ui.add(gate_knob);
+                                        }
+                                    });
+                                });
+                            }
+
+                            if *page == EditorPage::Modulation {
+                                ui.horizontal(|ui| {
+                                    ui.add_space(16.0 * scale);
+                                    ui.vertical(|ui| {
+                                        let knob_size = 36.0 * scale;
+                                        let text_size = 12.0 * scale;
+                                        ui.horizontal(|ui| {
+                                            for (number, lfo) in params.lfos.iter().enumerate() {
+                                                ui.vertical(|ui| {
+                                                    ui.label(RichText::new(format!("LFO {}", number + 1)).size(text_size).color(theme.primary));
+                                                    ui.add(widgets::ParamSlider::for_param(&lfo.shape, setter).with_width(90.0 * scale));
+                                                    let rate_knob = ui_knob::ArcKnob::for_param(
+                                                        &lfo.rate, 
+                                                        setter, 
+                                                        knob_size, 
+                                                        ui_knob::KnobLayout::Horizonal)
+                                                            .preset_style(ui_knob::KnobStyle::Preset1)
+                                                            .set_theme(&theme)
+                                                            .set_text_size(text_size)
+                                                            .set_hover_text("LFO speed when it isn't synced".to_string());
+                                                    ui.add(rate_knob);
+                                                    let mut sync = lfo.sync.value();
+                                                    if ui.checkbox(&mut sync, RichText::new("Sync").size(text_size).color(theme.primary))
+                                                        .on_hover_text("Follow the host tempo and position using the division below")
+                                                        .changed()
+                                                    {
+                                                        setter.begin_set_parameter(&lfo.sync);
+                                                        setter.set_parameter(&lfo.sync, sync);
+                                                        setter.end_set_parameter(&lfo.sync);
+                                                    }
+                                                    ui.add(widgets::ParamSlider::for_param(&lfo.division, setter).with_width(90.0 * scale));
+                                                });
+                                            }
+                                            ui.vertical(|ui| {
+                                                ui.label(RichText::new("Envelope").size(text_size).color(theme.primary));
+                                                for (param, hover) in [
+                                                    (&params.mod_env_attack, "How quickly the envelope follows a rising input"),
+                                                    (&params.mod_env_release, "How quickly the envelope follows a falling input"),
+                                                ] {
+                                                    let envelope_knob = ui_knob::ArcKnob::for_param(
+                                                        param, 
+                                                        setter, 
+                                                        knob_size, 
+                                                        ui_knob::KnobLayout::Horizonal)
+                                                            .preset_style(ui_knob::KnobStyle::Preset1)
+                                                            .set_theme(&theme)
+                                                            .set_text_size(text_size)
+                                                            .set_hover_text(hover.to_string());
+                                                    ui.add(envelope_knob);
+                                                }
+                                            });
+                                        });
+
+                                        ui.add_space(8.0 * scale);
+                                        ui.label(RichText::new("Mod Matrix").size(text_size).color(theme.primary));
+                                        egui::Grid::new("mod-matrix").spacing(egui::vec2(4.0 * scale, 4.0 * scale)).show(ui, |ui| {
+                                            for slot in params.mod_slots.iter() {
+                                                ui.add(widgets::ParamSlider::for_param(&slot.source, setter).with_width(90.0 * scale))
+                                                    .on_hover_text_at_pointer("What moves the target");
+                                                ui.add(widgets::ParamSlider::for_param(&slot.target, setter).with_width(90.0 * scale))
+                                                    .on_hover_text_at_pointer("Which knob gets moved");
+                                                ui.add(widgets::ParamSlider::for_param(&slot.depth, setter).with_width(90.0 * scale))
+                                                    .on_hover_text_at_pointer("How far the knob moves, as a share of its range. Negative goes the other way");
+                                                ui.end_row();
+                                            }
+                                        });
+                                    });
+                                });
+                            }
+
+                            if *page == EditorPage::Visualizer {
+                                ui.horizontal(|ui| {
+                                    ui.add_space(16.0 * scale);
+                                    ui.vertical(|ui| {
+                                        ui.label(RichText::new("Transfer Curve").size(12.0 * scale).color(theme.primary));
+                                        let settings = HarmonicSettings::from_params(&params, 1.0);
+                                        let algorithm = params.h_algorithm.value();
+                                        let points: Vec<f32> = (0..CURVE_POINTS)
+                                            .map(|idx| transfer_curve_point(algorithm, idx as f32 / (CURVE_POINTS - 1) as f32 * 2.0 - 1.0, &settings))
+                                            .collect();
+                                        let curve_size = (WIDTH as f32 - 32.0) * scale;
+                                        ui.add(signal_plot::TransferCurve::new(points, egui::vec2(curve_size, curve_size * 0.75)).set_theme(&theme))
+                                            .on_hover_text_at_pointer("Input (left to right) against output (bottom to top) for the current algorithm, harmonics and hardness");
+
+                                        ui.label(RichText::new("Scope").size(12.0 * scale).color(theme.primary));
+                                        ui.add(signal_plot::Scope::new(scope_in.snapshot(), scope_out.snapshot(), egui::vec2(curve_size, curve_size * 0.5)).set_theme(&theme))
+                                            .on_hover_text_at_pointer("The last few milliseconds of input (thin) and output (thick)");
+                                    });
+                                });
+                            }
+
+                            if *page == EditorPage::Settings {
+                                ui.horizontal(|ui| {
+                                    ui.add_space(16.0 * scale);
+                                    ui.vertical(|ui| {
+                                        let knob_size = 42.0 * scale;
+                                        let text_size = 12.0 * scale;
+                                        let smoothing_knob = ui_knob::ArcKnob::for_param(
+                                            &params.smoothing_time, 
+                                            setter, 
+                                            knob_size, 
+                                            ui_knob::KnobLayout::Horizonal)
+                                                .preset_style(ui_knob::KnobStyle::Preset1)
+                                                .set_theme(&theme)
+                                                .set_text_size(text_size)
+                                                .set_hover_text("How quickly hardness, harmonics, sub, custom, harmonic EQ and mid/side knobs follow changes and automation".to_string());
+                                        ui.add(smoothing_knob);
+
+                                        let crossfade_knob = ui_knob::ArcKnob::for_param(
+                                            &params.algorithm_crossfade, 
+                                            setter, 
+                                            knob_size, 
+                                            ui_knob::KnobLayout::Horizonal)
+                                                .preset_style(ui_knob::KnobStyle::Preset1)
+                                                .set_theme(&theme)
+                                                .set_text_size(text_size)
+                                                .set_hover_text("Blend time when the harmonic algorithm changes to avoid clicks".to_string());
+                                        ui.add(crossfade_knob);
+
+                                        let lookahead_knob = ui_knob::ArcKnob::for_param(
+                                            &params.lookahead, 
+                                            setter, 
+                                            knob_size, 
+                                            ui_knob::KnobLayout::Horizonal)
+                                                .preset_style(ui_knob::KnobStyle::Preset1)
+                                                .set_theme(&theme)
+                                                .set_text_size(text_size)
+                                                .set_hover_text("Delay the audio so the sub gate opens before notes play. Adds this much latency".to_string());
+                                        ui.add(lookahead_knob);
+
+                                        // Surround routing only matters in 5.1 and 7.1
+                                        ui.add_space(8.0 * scale);
+                                        ui.label(RichText::new("Surround (5.1 / 7.1)").size(text_size).color(theme.primary));
+                                        ui.horizontal(|ui| {
+                                            ui.label(RichText::new("Sub Destination").size(text_size).color(theme.secondary));
+                                            ui.add(widgets::ParamSlider::for_param(&params.sub_destination, setter).with_width(120.0 * scale))
+                                                .on_hover_text_at_pointer("Send the sub to the front mains, the LFE channel or both");
+                                        });
+                                        for (param, name, hover) in [
+                                            (&params.center_harmonics, "Center Harmonics", "Add harmonics to the center channel and use it for the sub"),
+                                            (&params.surround_harmonics, "Surround Harmonics", "Add harmonics to the side and rear channels and use them for the sub"),
+                                        ] {
+                                            let mut enabled = param.value();
+                                            if ui.checkbox(&mut enabled, RichText::new(name).size(text_size).color(theme.primary))
+                                                .on_hover_text(hover)
+                                                .changed()
+                                            {
+                                                setter.begin_set_parameter(param);
+                                                setter.set_parameter(param, enabled);
+                                                setter.end_set_parameter(param);
+                                            }
+                                        }
+
+                                        // Stereo and mono have aux outputs with the sub and harmonics by themselves
+                                        ui.add_space(8.0 * scale);
+                                        ui.label(RichText::new("Aux Outputs").size(text_size).color(theme.primary));
+                                        for (param, name, hover) in [
+                                            (&params.separate_sub, "Separate Sub", "Only send the sub to the \"Sub\" aux output, not the main output"),
+                                            (&params.separate_harmonics, "Separate Harmonics", "Only send the harmonics to the \"Harmonics\" aux output, not the main output"),
+                                        ] {
+                                            let mut enabled = param.value();
+                                            if ui.checkbox(&mut enabled, RichText::new(name).size(text_size).color(theme.primary))
+                                                .on_hover_text(hover)
+                                                .changed()
+                                            {
+                                                setter.begin_set_parameter(param);
+                                                setter.set_parameter(param, enabled);
+                                                setter.end_set_parameter(param);
+                                            }
+                                        }
+                                    });
+                                });
+                            }
                         });
                     });
                 }
@@ -652,13 +3043,25 @@ Double-click to reset");
 
     fn initialize(
         &mut self,
-        _audio_io_layout: &AudioIOLayout,
+        audio_io_layout: &AudioIOLayout,
         buffer_config: &BufferConfig,
-        _context: &mut impl InitContext<Self>,
+        context: &mut impl InitContext<Self>,
     ) -> bool {
         // After `PEAK_METER_DECAY_MS` milliseconds of pure silence, the peak meter's value should
         // have dropped by 12 dB
         self.out_meter_decay_weight = 0.25f64.powf((buffer_config.sample_rate as f64 * PEAK_METER_DECAY_MS / 1000.0).recip()) as f32;
+
+        // Start the ramps and algorithm on the loaded values so nothing fades in
+        self.ramps = ParamRamps::new(&self.params);
+        self.current_algorithm = self.params.h_algorithm.value();
+        self.fade_samples_left = 0;
+
+        // Room for the longest lookahead, and the latency for the current one
+        let num_channels: usize = audio_io_layout.main_input_channels.map_or(0, |channels| channels.get() as usize);
+        self.lookahead.resize(num_channels, buffer_config.sample_rate);
+        let lookahead: usize = (self.params.lookahead.value() * 0.001 * buffer_config.sample_rate).round() as usize;
+        self.lookahead.set_delay(lookahead);
+        context.set_latency_samples(self.lookahead.delay() as u32);
         
         nih_dbg!("Plugin started successfully");
         color_backtrace::install();
@@ -669,379 +3072,57 @@ Double-click to reset");
     fn process(
         &mut self,
         buffer: &mut Buffer,
-        _aux: &mut AuxiliaryBuffers,
+        aux: &mut AuxiliaryBuffers,
         context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
-        for mut channel_samples in buffer.iter_samples() {
-            let mut out_amplitude: f32 = 0.0;
-            let mut in_amplitude: f32 = 0.0;
-            let mut processed_sample_l: f32;
-            let mut processed_sample_r: f32;
-            let num_samples = channel_samples.len();
-
-            let gain: f32 = util::gain_to_db(self.params.free_gain.smoothed.next());
-            let num_gain: f32;
-            let hoof_hardness: f32 = self.params.hoof_hardness.smoothed.next();
-            let sub_gain: f32 = self.params.sub_gain.smoothed.next();
-            let output_gain: f32 = self.params.output_gain.smoothed.next();
-            let sub_drive: f32 = self.params.sub_drive.smoothed.next();
-            let harmonics: f32 = self.params.harmonics.smoothed.next();
-            let custom_harmonics1: f32 = self.params.custom_harmonics1.smoothed.next();
-            let custom_harmonics2: f32 = self.params.custom_harmonics2.smoothed.next();
-            let custom_harmonics3: f32 = self.params.custom_harmonics3.smoothed.next();
-            let custom_harmonics4: f32 = self.params.custom_harmonics4.smoothed.next();
-            let h_algorithm: AlgorithmType = self.params.h_algorithm.value();
-            let dry_wet: f32 = self.params.dry_wet.value();
-
-            // I picked this
-            let mut fake_random: f32 = 0.83;
-            let inv_fake_random: f32 = 1.0 - fake_random;
-            fake_random /= 2.0;
-
-            // Scale the head bump freqeuncy for Subhoof
-            let sample_rate: f32 = context.transport().sample_rate;
-            let mut overall_scale: f32 = 1.0;
-            overall_scale /= 44100.0;
-            overall_scale *= sample_rate;
-
-            // Split left and right same way original subhoofer did
-            let mut in_l = *channel_samples.get_mut(0).unwrap();
-            let mut in_r = *channel_samples.get_mut(1).unwrap();
-
-            num_gain = gain;
-            in_l *= util::db_to_gain(num_gain);
-            in_r *= util::db_to_gain(num_gain);
-            in_amplitude += in_l + in_r;
-
-            ///////////////////////////////////////////////////////////////////////
-            // Perform processing on the sample
-
-            // Normalize really small values
-            if in_l.abs() < 1.18e-23 { in_l = 0.1 * 1.18e-17; }
-            if in_r.abs() < 1.18e-23 { in_r = 0.1 * 1.18e-17; }
-
-            let mut sub_bump: f32;
-
-            // Sub voicing variables
-            let sub_headbump_freq: f32 = (((hoof_hardness) * 0.1) + 0.02) / overall_scale;
-            self.sub_iir = sub_headbump_freq / 44.1;
-
-            // Sub drive samples
-            // self.lp is our center signal
-            self.lp = (in_l + in_r) / 4096.0;
-            self.iir_drive_sample_a = (self.iir_drive_sample_a * (1.0 - sub_headbump_freq)) + (self.lp * sub_headbump_freq);
-            self.lp = self.iir_drive_sample_a;
-            self.iir_drive_sample_b = (self.iir_drive_sample_b * (1.0 - sub_headbump_freq)) + (self.lp * sub_headbump_freq);
-            self.lp = self.iir_drive_sample_b;
-            // Gate from airwindows
-            self.osc_gate += (self.lp * 10.0).abs();
-            self.osc_gate -= 0.001;
-            if self.osc_gate > 1.0 {self.osc_gate = 1.0;}
-            if self.osc_gate < 0.0 {self.osc_gate = 0.0;}
-            //got a value that only goes down low when there's silence or near silence on input
-            let clamp: f32 = (1.0 - self.osc_gate) * 0.00001;
-            // Figure out our zero crossing
-            if self.lp > 0.0
-            {
-                // We are on top of zero crossing
-                if self.was_negative
-                {
-                    self.sub_octave = !self.sub_octave;
-                    self.was_negative = false;
-                }
-            }
-            else {
-                // On bottom of zero crossing
-                self.was_negative = true;
-            }
-            self.iir_sample_a = (self.iir_sample_a * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_a;
-			self.iir_sample_b = (self.iir_sample_b * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_b;
-			self.iir_sample_c = (self.iir_sample_c * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_c;
-			self.iir_sample_d = (self.iir_sample_d * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_d;
-			self.iir_sample_e = (self.iir_sample_e * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_e;
-			self.iir_sample_f = (self.iir_sample_f * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_f;
-			self.iir_sample_g = (self.iir_sample_g * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_g;
-			self.iir_sample_h = (self.iir_sample_h * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_h;
-			self.iir_sample_i = (self.iir_sample_i * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_i;
-			self.iir_sample_j = (self.iir_sample_j * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_j;
-			self.iir_sample_k = (self.iir_sample_k * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_k;
-			self.iir_sample_l = (self.iir_sample_l * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_l;
-			self.iir_sample_m = (self.iir_sample_m * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_m;
-			self.iir_sample_n = (self.iir_sample_n * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_n;
-			self.iir_sample_o = (self.iir_sample_o * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_o;
-			self.iir_sample_p = (self.iir_sample_p * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_p;
-			self.iir_sample_q = (self.iir_sample_q * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_q;
-			self.iir_sample_r = (self.iir_sample_r * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_r;
-			self.iir_sample_s = (self.iir_sample_s * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_s;
-			self.iir_sample_t = (self.iir_sample_t * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_t;
-			self.iir_sample_u = (self.iir_sample_u * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_u;
-			self.iir_sample_v = (self.iir_sample_v * (1.0 - self.sub_iir)) + (self.lp * self.sub_iir);  self.lp -= self.iir_sample_v;
-            let mut head_bump: f32 = self.lp;
-
-            // Regain some volume now that we have sampled
-            head_bump = head_bump * 256.0;
-
-            // Calculate drive samples based off the processing so far
-            self.iir_sample_w = (self.iir_sample_w * (1.0 - self.sub_iir)) + (head_bump * self.sub_iir);    head_bump -= self.iir_sample_w;
-			self.iir_sample_x = (self.iir_sample_x * (1.0 - self.sub_iir)) + (head_bump * self.sub_iir);    head_bump -= self.iir_sample_x;
-
-            // Create SubBump sample from our head bump to modify further
-			sub_bump = head_bump;
-			self.iir_sample_y = (self.iir_sample_y * (1.0 - self.sub_iir)) + (sub_bump * self.sub_iir);    sub_bump -= self.iir_sample_y;
-
-            // Calculate sub drive samples based off what we've done so far		
-            self.iir_drive_sample_c = (self.iir_drive_sample_c * (1.0 - sub_headbump_freq)) + (sub_bump * sub_headbump_freq);   sub_bump = self.iir_drive_sample_c;
-            self.iir_drive_sample_d = (self.iir_drive_sample_d * (1.0 - sub_headbump_freq)) + (sub_bump * sub_headbump_freq);   sub_bump = self.iir_drive_sample_d;
+        // Scale the head bump freqeuncy for Subhoof
+        let sample_rate: f32 = context.transport().sample_rate;
+
+        // Report a new lookahead to the host as latency
+        let lookahead: usize = (self.params.lookahead.value() * 0.001 * sample_rate).round() as usize;
+        if lookahead != self.lookahead.delay() {
+            self.lookahead.set_delay(lookahead);
+            context.set_latency_samples(self.lookahead.delay() as u32);
+        }
 
-            // Flip the bump sample per sub octave for half-freq
-            sub_bump = sub_bump.abs();
-            sub_bump = if self.sub_octave == false { -sub_bump } else { sub_bump };
-            // Note the randD/invrandD is what is flipping from positive to negative here
-			// This means bflip = 1 A gets inverted
-			// This means bflip = 2 B gets inverted
-			// This means bflip = 3 C gets inverted
-			// This creates a lower octave using  multiplication depending on sample
-            match self.bass_flip_counter
-            {
-                1 => {
-                    self.iir_sub_bump_a += sub_bump * sub_gain;
-                    self.iir_sub_bump_a -= self.iir_sub_bump_a * self.iir_sub_bump_a * self.iir_sub_bump_a * sub_headbump_freq;
-                    self.iir_sub_bump_a = (inv_fake_random * self.iir_sub_bump_a) + (fake_random * self.iir_sub_bump_b) + (fake_random * self.iir_sub_bump_c);
-                    if self.iir_sub_bump_a > 0.0 { self.iir_sub_bump_a -= clamp; }
-                    if self.iir_sub_bump_a < 0.0 { self.iir_sub_bump_a += clamp; }
-                    sub_bump = self.iir_sub_bump_a;
-                }
-                2 => {
-                    self.iir_sub_bump_b += sub_bump * sub_gain;
-                    self.iir_sub_bump_b -= self.iir_sub_bump_b * self.iir_sub_bump_b * self.iir_sub_bump_b * sub_headbump_freq;
-                    self.iir_sub_bump_b = (fake_random * self.iir_sub_bump_a) + (inv_fake_random * self.iir_sub_bump_b) + (fake_random * self.iir_sub_bump_c);
-                    if self.iir_sub_bump_b > 0.0 { self.iir_sub_bump_b -= clamp; }
-                    if self.iir_sub_bump_b < 0.0 { self.iir_sub_bump_b += clamp; }
-                    sub_bump = self.iir_sub_bump_b;
-                }
-                3 => {
-                    self.iir_sub_bump_c += sub_bump * sub_gain;
-                    self.iir_sub_bump_c -= self.iir_sub_bump_c * self.iir_sub_bump_c * self.iir_sub_bump_c * sub_headbump_freq;
-                    self.iir_sub_bump_c = (fake_random * self.iir_sub_bump_a) + (fake_random * self.iir_sub_bump_b) + (inv_fake_random * self.iir_sub_bump_c);
-                    if self.iir_sub_bump_c > 0.0 { self.iir_sub_bump_c -= clamp; }
-                    if self.iir_sub_bump_c < 0.0 { self.iir_sub_bump_c += clamp; }
-                    sub_bump = self.iir_sub_bump_c;
-                }
-                _ => unreachable!()
+        // Only fill the aux layers when the host gave us somewhere for them to go
+        self.aux_outputs = aux.outputs.iter().any(|output| output.channels() > 0 && output.samples() > 0);
+
+        // Tempo and position for synced LFOs, process_block moves these along
+        let transport = context.transport();
+        self.host_clock.tempo = transport.tempo.unwrap_or(120.0);
+        self.host_clock.beats = if transport.playing { transport.pos_beats() } else { None };
+
+        // Hand the channels over a block at a time
+        let channels = buffer.as_slice();
+        let num_channels: usize = channels.len().min(MAX_CHANNELS);
+        let num_samples: usize = channels.first().map_or(0, |channel| channel.len());
+        let mut block_start: usize = 0;
+        while block_start < num_samples {
+            let block_end: usize = (block_start + BLOCK_SIZE).min(num_samples);
+            let mut block: [&mut [f32]; MAX_CHANNELS] = Default::default();
+            for (block_channel, channel) in block.iter_mut().zip(channels.iter_mut()) {
+                *block_channel = &mut channel[block_start..block_end];
             }
-            // Resample to reduce the sub bump further
-            self.iir_sample_z = (self.iir_sample_z * (1.0 - sub_headbump_freq)) + (sub_bump * sub_headbump_freq);
-            sub_bump = self.iir_sample_z;
-            self.iir_drive_sample_e = (self.iir_drive_sample_e * (1.0 - self.sub_iir)) + (sub_bump * self.sub_iir);
-            sub_bump = self.iir_drive_sample_e;
-            self.iir_drive_sample_f = (self.iir_drive_sample_f * (1.0 - self.sub_iir)) + (sub_bump * self.sub_iir);
-            sub_bump = self.iir_drive_sample_f;
-
-            // Calculate our final sub drive
-            if sub_drive > 0.0
-            {
-                sub_bump += tape_saturation(sub_bump, sub_drive);
-            }
-            
-            // Add: Original signal + Harmonics + Sub signal
-            match h_algorithm {
-                AlgorithmType::ABass3 => {
-                    let harmonic2_l: f32;
-                    let harmonic2_r: f32;
-                    let harmonic3_l: f32;
-                    let harmonic3_r: f32;
-                    let harmonic4_l: f32;
-                    let harmonic4_r: f32;
-                    let harmonic5_l: f32;
-                    let harmonic5_r: f32;
-                    let harmonic6_l: f32;
-                    let harmonic6_r: f32;
-                    let harmonic7_l: f32;
-                    let harmonic7_r: f32;
-                    let harmonic8_l: f32;
-                    let harmonic8_r: f32;
-                    let harmonic9_l: f32;
-                    let harmonic9_r: f32;
-                    let harmonic10_l: f32;
-                    let harmonic10_r: f32;
-                    let harmonic11_l: f32;
-                    let harmonic11_r: f32;
-                    let harmonic12_l: f32;
-                    let harmonic12_r: f32;
-                    let harmonic13_l: f32;
-                    let harmonic13_r: f32;
-                    let harmonic14_l: f32;
-                    let harmonic14_r: f32;
-
-                    (harmonic2_l, harmonic2_r) = SweetenX::process(in_l, in_r, overall_scale, 26.470589, 2, &mut self.buffer);
-                    (harmonic3_l, harmonic3_r) = SweetenX::process(in_l, in_r, overall_scale, 8.941176, 3, &mut self.buffer);
-                    (harmonic4_l, harmonic4_r) = SweetenX::process(in_l, in_r, overall_scale, 0.1764706, 4, &mut self.buffer);
-                    (harmonic5_l, harmonic5_r) = SweetenX::process(in_l, in_r, overall_scale, 0.0, 5, &mut self.buffer);
-                    (harmonic6_l, harmonic6_r) = SweetenX::process(in_l, in_r, overall_scale, 0.0, 6, &mut self.buffer);
-                    (harmonic7_l, harmonic7_r) = SweetenX::process(in_l, in_r, overall_scale, 0.0, 7, &mut self.buffer);
-                    (harmonic8_l, harmonic8_r) = SweetenX::process(in_l, in_r, overall_scale, 0.0, 8, &mut self.buffer);
-                    (harmonic9_l, harmonic9_r) = SweetenX::process(in_l, in_r, overall_scale, 171.76471, 9, &mut self.buffer);
-
-                    let octave_l = in_l * in_l * in_l * in_l * in_l * 0.5;
-                    let octave_r = in_r * in_r * in_r * in_r * in_r * 0.5;
-                    // Start from 5th
-                    (harmonic10_l, harmonic10_r) = SweetenX::process(octave_l, octave_r, overall_scale, 0.0, 2, &mut self.buffer);
-                    (harmonic11_l, harmonic11_r) = SweetenX::process(octave_l, octave_r, overall_scale, 4000.0, 3, &mut self.buffer);
-                    (harmonic12_l, harmonic12_r) = SweetenX::process(octave_l, octave_r, overall_scale, 11764706.0, 4, &mut self.buffer);
-                    (harmonic13_l, harmonic13_r) = SweetenX::process(octave_l, octave_r, overall_scale, 5294118000.0, 5, &mut self.buffer);
-                    (harmonic14_l, harmonic14_r) = SweetenX::process(octave_l, octave_r, overall_scale, 17647059000.0, 6, &mut self.buffer);
-
-                    processed_sample_l = in_l;
-                    processed_sample_r = in_r;
-
-                    // Sum all harmonics into the processed sample
-                    //processed_sample_l += harmonic2_l + harmonic3_l + (harmonic7_l - harmonic5_l*2.0 - harmonic3_l*2.0 - harmonic2_l*2.0) + (harmonic9_l - harmonic2_l*2.0) + (sub_bump * sub_gain);
-                    processed_sample_l += (harmonic2_l + harmonic3_l + harmonic4_l + harmonic5_l + 
-                        harmonic6_l + harmonic7_l + harmonic8_l + harmonic9_l + 
-                        harmonic10_l + harmonic11_l + harmonic12_l + harmonic13_l + 
-                        harmonic14_l)*(harmonics * 1497.00599) + (sub_bump * sub_gain);
-                    //processed_sample_r += harmonic2_r + harmonic3_r + (harmonic7_r - harmonic5_r*2.0 - harmonic3_r*2.0 - harmonic2_r*2.0) + (harmonic9_r - harmonic2_r*2.0) + (sub_bump * sub_gain);
-                    processed_sample_r += (harmonic2_r + harmonic3_r + harmonic4_r + harmonic5_r + 
-                        harmonic6_r + harmonic7_r + harmonic8_r + harmonic9_r + 
-                        harmonic10_r + harmonic11_r + harmonic12_r + harmonic13_r + 
-                        harmonic14_r)*(harmonics * 1497.00599) + (sub_bump * sub_gain);
-
-                    // Scaling
-                    let scale = util::db_to_gain(-21.2);
-                    processed_sample_l *= scale;
-                    processed_sample_r *= scale;
 
-                }
-                AlgorithmType::ABass2 => {
-                    // Ardura's new Algorithm for 2024
-                    processed_sample_l = custom_sincos_saturation(
-                        in_l, 
-                        harmonics * 31.422043, 
-                        harmonics * 189.29568, 
-                        harmonics * 25.0, 
-                        harmonics * 26.197401) + (sub_bump * sub_gain);
-                    processed_sample_r = custom_sincos_saturation(
-                        in_l, 
-                        harmonics * 31.422043, 
-                        harmonics * 189.29568, 
-                        harmonics * 25.0, 
-                        harmonics * 26.197401) + (sub_bump * sub_gain);
-                    let h_l = (processed_sample_l * 2.0) - processed_sample_l.powf(2.0);
-                    let h_r = (processed_sample_r * 2.0) - processed_sample_r.powf(2.0);
-                    processed_sample_l += h_l * 0.0070118904;
-                    processed_sample_r += h_r * 0.0070118904;
-                    processed_sample_l = util::db_to_gain(-2.4)*processed_sample_l;
-                    processed_sample_r = util::db_to_gain(-2.4)*processed_sample_r;
-                },
-                AlgorithmType::BBass => {
-                    // C3 signal in RBass is C3, C4, G4, C5, E5, A#5, D6, F#6
-                    processed_sample_l = b_bass_saturation(in_l, harmonics) + (sub_bump * sub_gain);
-                    processed_sample_r = b_bass_saturation(in_r, harmonics) + (sub_bump * sub_gain);
-                    processed_sample_l = util::db_to_gain(8.7)*processed_sample_l;
-                    processed_sample_r = util::db_to_gain(8.7)*processed_sample_r;
-                },
-                AlgorithmType::CBass => {
-                    if harmonics > 0.0 {
-                        processed_sample_l = c_bass_saturation(in_l, harmonics) + (sub_bump * sub_gain);
-                        processed_sample_r = c_bass_saturation(in_r, harmonics) + (sub_bump * sub_gain);
-                    } else {
-                        processed_sample_l = sub_bump * sub_gain;
-                        processed_sample_r = sub_bump * sub_gain;
+            // Peak of the sidechain for sub ducking
+            self.sidechain = [0.0; BLOCK_SIZE];
+            if let Some(sidechain) = aux.inputs.first() {
+                for channel in sidechain.as_slice_immutable().iter() {
+                    for (peak, sample) in self.sidechain.iter_mut().zip(channel[block_start..block_end].iter()) {
+                        *peak = peak.max(sample.abs());
                     }
                 }
-                AlgorithmType::TanH => {
-                    // Generate tanh curve harmonics gently
-                    processed_sample_l = tape_saturation(in_l, harmonics) + (sub_bump * sub_gain);
-                    processed_sample_r = tape_saturation(in_r, harmonics) + (sub_bump * sub_gain);
-                    processed_sample_l = util::db_to_gain(8.0)*processed_sample_l;
-                    processed_sample_r = util::db_to_gain(8.0)*processed_sample_r;
-                },
-                AlgorithmType::CustomSliders => {
-                    processed_sample_l = custom_sincos_saturation(in_l, harmonics*custom_harmonics1, harmonics*custom_harmonics2, harmonics*custom_harmonics3, harmonics*custom_harmonics4) + (sub_bump * sub_gain);
-                    processed_sample_r = custom_sincos_saturation(in_r, harmonics*custom_harmonics1, harmonics*custom_harmonics2, harmonics*custom_harmonics3, harmonics*custom_harmonics4) + (sub_bump * sub_gain);
-                    processed_sample_l = util::db_to_gain(-4.2)*processed_sample_l;
-                    processed_sample_r = util::db_to_gain(-4.2)*processed_sample_r;
-                },
-            }
-
-            // Hardness Saturation
-            if h_algorithm == AlgorithmType::ABass3 {
-                let leaf_wet_l: f32;
-                let leaf_wet_r: f32;
-                let threshold: f32 = util::db_to_gain(-30.0);
-                leaf_wet_l = leaf_saturation(in_l, threshold, 0.5);
-                leaf_wet_r = leaf_saturation(in_r, threshold, 0.5);
-                let scaler = 0.0016129*hoof_hardness*100.0; //0.0015 default;
-                processed_sample_l = scaler*leaf_wet_l + (1.0 - scaler)*processed_sample_l;
-                processed_sample_r = scaler*leaf_wet_r + (1.0 - scaler)*processed_sample_r;
-            } else {
-                processed_sample_l = chebyshev_tape(processed_sample_l, hoof_hardness);
-                processed_sample_r = chebyshev_tape(processed_sample_r, hoof_hardness);
             }
-            
-            
-            // Increment/change the bass_flip_counter
-            self.bass_flip_counter += 1;
-            self.bass_flip_counter = 
-                if self.bass_flip_counter < 1 || self.bass_flip_counter > 3 { 1 } 
-                else { self.bass_flip_counter };
-            
-            // Remove DC Offset with single pole HP
-            let hp_b0: f32 = 1.0;
-            let hp_b1: f32 = -1.0;
-            let hp_a1: f32 = -0.995;
-        
-            // Calculated below by Ardura in advance!
-            // double sqrt2 = 1.41421356237;
-            // double corner_frequency = 5.0 / sqrt2;
-            // double hp_gain = 1 / sqrt(1 + (5.0 / (corner_frequency)) ^ 2);
-            //let hp_gain = 0.577350269190468;
-            let hp_gain = 1.0;
-        
-            // Apply the 1 pole HP to left side
-            processed_sample_l = hp_gain * processed_sample_l;
-            let temp_sample: f32 = hp_b0 * processed_sample_l + hp_b1 * self.prev_processed_in_l - hp_a1 * self.prev_processed_out_l;
-            self.prev_processed_in_l = processed_sample_l;
-            self.prev_processed_out_l = temp_sample;
-            processed_sample_l = temp_sample;
-
-            // Apply the 1 pole HP to right side
-            processed_sample_r = hp_gain * processed_sample_r;
-            let temp_sample: f32 = hp_b0 * processed_sample_r + hp_b1 * self.prev_processed_in_r - hp_a1 * self.prev_processed_out_r;
-            self.prev_processed_in_r = processed_sample_r;
-            self.prev_processed_out_r = temp_sample;
-            processed_sample_r = temp_sample;
-            
-            ///////////////////////////////////////////////////////////////////////
-
-            // Calculate dry/wet mix
-            let wet_gain: f32 = dry_wet;
-            processed_sample_l = in_l + processed_sample_l * wet_gain;
-            processed_sample_r = in_r + processed_sample_r * wet_gain;
-
-            // get the output amplitude here
-            processed_sample_l = processed_sample_l*output_gain;
-            processed_sample_r = processed_sample_r*output_gain;
-            out_amplitude += processed_sample_l + processed_sample_r;
-
-            // Assign back so we can output our processed sounds
-            *channel_samples.get_mut(0).unwrap() = processed_sample_l;
-            *channel_samples.get_mut(1).unwrap() = processed_sample_r;
-
-            // calculations that are only displayed on the GUI while the GUI is open
-            if self.params.editor_state.is_open() {
-                // Input gain meter
-                in_amplitude = (in_amplitude / num_samples as f32).abs();
-                let current_in_meter = self.in_meter.load(std::sync::atomic::Ordering::Relaxed);
-                let new_in_meter = if in_amplitude > current_in_meter {in_amplitude} else {current_in_meter * self.out_meter_decay_weight + in_amplitude * (1.0 - self.out_meter_decay_weight)};
-                self.in_meter.store(new_in_meter, std::sync::atomic::Ordering::Relaxed);
+            self.process_block(&mut block[..num_channels], sample_rate);
 
-                // Output gain meter
-                out_amplitude = (out_amplitude / num_samples as f32).abs();
-                let current_out_meter = self.out_meter.load(std::sync::atomic::Ordering::Relaxed);
-                let new_out_meter = if out_amplitude > current_out_meter {out_amplitude} else {current_out_meter * self.out_meter_decay_weight + out_amplitude * (1.0 - self.out_meter_decay_weight)};
-                self.out_meter.store(new_out_meter, std::sync::atomic::Ordering::Relaxed);
+            // Copy the sub and harmonic layers out to the aux outputs
+            for (layer, output) in [&self.sub_layer, &self.harmonic_layer].iter().zip(aux.outputs.iter_mut()) {
+                for (lane, channel) in output.as_slice().iter_mut().enumerate() {
+                    channel[block_start..block_end].copy_from_slice(&layer[lane.min(1)][..block_end - block_start]);
+                }
             }
+            block_start = block_end;
         }
 
         ProcessStatus::Normal
@@ -1058,7 +3139,9 @@ Double-click to reset");
         Box::new(|_| ())
     }
 
-    fn filter_state(_state: &mut PluginState) {}
+    fn filter_state(state: &mut PluginState) {
+        StateMigration::migrate(state);
+    }
 
     fn reset(&mut self) {
         nih_dbg!("Plugin resetting...");
@@ -1090,7 +3173,7 @@ nih_export_clap!(Subhoofer);
 nih_export_vst3!(Subhoofer);
 
 // "Leaf" Saturation designed by Ardura
-fn leaf_saturation(input_signal: f32, threshold: f32, drive: f32) -> f32 {
+pub fn leaf_saturation(input_signal: f32, threshold: f32, drive: f32) -> f32 {
     let range = 6.0;
     let min_value = 1.0;
     let drive_db = min_value + drive * range;
@@ -1101,4 +3184,212 @@ fn leaf_saturation(input_signal: f32, threshold: f32, drive: f32) -> f32 {
     let mut y = signal_holder / threshold;
     y = (2.0 / PI) * y.atan();
     (threshold + (1.0 - threshold) * curve) * y
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44100.0;
+    const BURST_SECONDS: f32 = 0.25;
+    // The gate closing plus the output DC blockers running down to zero
+    const SILENCE_SECONDS: f32 = 0.75;
+    const CHECK_SECONDS: f32 = 0.25;
+
+    // Mono, stereo and 5.1
+    const CHANNEL_COUNTS: [usize; 3] = [1, 2, 6];
+
+    // The plugin wrapper flushes denormals to zero around process(), this does the same for the
+    // tests and puts the old flags back when dropped
+    struct ScopedFtz {
+        #[cfg(target_arch = "x86_64")]
+        previous: u32,
+        #[cfg(target_arch = "aarch64")]
+        previous: u64,
+    }
+
+    impl ScopedFtz {
+        fn enable() -> Self {
+            #[cfg(target_arch = "x86_64")]
+            {
+                // FTZ is bit 15 and DAZ bit 6 of MXCSR
+                let mut previous: u32 = 0;
+                unsafe { std::arch::asm!("stmxcsr [{}]", in(reg) &mut previous as *mut u32, options(nostack)) };
+                let flags: u32 = previous | 0x8040;
+                unsafe { std::arch::asm!("ldmxcsr [{}]", in(reg) &flags as *const u32, options(nostack, readonly)) };
+                Self { previous }
+            }
+
+            #[cfg(target_arch = "aarch64")]
+            {
+                // FZ is bit 24 of FPCR, it covers inputs and outputs
+                let previous: u64;
+                unsafe {
+                    std::arch::asm!("mrs {}, fpcr", out(reg) previous, options(nomem, nostack));
+                    std::arch::asm!("msr fpcr, {}", in(reg) previous | (1 << 24), options(nomem, nostack));
+                }
+                Self { previous }
+            }
+        }
+    }
+
+    impl Drop for ScopedFtz {
+        fn drop(&mut self) {
+            #[cfg(target_arch = "x86_64")]
+            unsafe { std::arch::asm!("ldmxcsr [{}]", in(reg) &self.previous as *const u32, options(nostack, readonly)) };
+
+            #[cfg(target_arch = "aarch64")]
+            unsafe { std::arch::asm!("msr fpcr, {}", in(reg) self.previous, options(nomem, nostack)) };
+        }
+    }
+
+    fn set_normalized(plugin: &Subhoofer, id: &str, normalized: f32) {
+        for (param_id, param_ptr, _) in plugin.params().param_map() {
+            if param_id == id {
+                unsafe { param_ptr.set_normalized_value(normalized) };
+            }
+        }
+    }
+
+    // A bass burst on every channel then silence, returns the last CHECK_SECONDS of each channel
+    fn tail_after_burst(algorithm: usize, num_channels: usize) -> Vec<Vec<f32>> {
+        let _ftz = ScopedFtz::enable();
+        let mut plugin = Subhoofer::default();
+        set_normalized(&plugin, "Algorithm", algorithm as f32 / (AlgorithmType::variants().len() - 1) as f32);
+        // The quickest release so the gate is shut well inside the silence
+        set_normalized(&plugin, "Gate Release", 0.0);
+
+        let burst: usize = (BURST_SECONDS * SAMPLE_RATE) as usize;
+        let check: usize = (CHECK_SECONDS * SAMPLE_RATE) as usize;
+        let total: usize = burst + (SILENCE_SECONDS * SAMPLE_RATE) as usize + check;
+        let mut channels: Vec<Vec<f32>> = (0..num_channels)
+            .map(|_| {
+                (0..total)
+                    .map(|idx| {
+                        if idx >= burst {
+                            return 0.0;
+                        }
+                        let t: f32 = idx as f32 / SAMPLE_RATE;
+                        0.5 * (2.0 * PI * 55.0 * t).sin() + 0.1 * (2.0 * PI * 440.0 * t).sin()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        for block_start in (0..total).step_by(BLOCK_SIZE) {
+            let block_end: usize = (block_start + BLOCK_SIZE).min(total);
+            let mut block: Vec<&mut [f32]> = channels.iter_mut().map(|channel| &mut channel[block_start..block_end]).collect();
+            plugin.process_block(&mut block, SAMPLE_RATE);
+        }
+        channels.into_iter().map(|channel| channel[total - check..].to_vec()).collect()
+    }
+
+    #[test]
+    fn silence_after_a_burst_is_exactly_zero() {
+        for num_channels in CHANNEL_COUNTS {
+            for (algorithm, name) in AlgorithmType::variants().iter().enumerate() {
+                let tail = tail_after_burst(algorithm, num_channels);
+                for (channel, samples) in tail.iter().enumerate() {
+                    if let Some(sample) = samples.iter().find(|sample| **sample != 0.0) {
+                        panic!("{name} with {num_channels} channels left {sample:e} on channel {channel} after the burst");
+                    }
+                }
+            }
+        }
+    }
+
+    // A note after a rest mustn't measure the rest as its period and fade the sub out under Sub Min
+    #[test]
+    fn rest_between_notes_keeps_the_sub_range() {
+        let _ftz = ScopedFtz::enable();
+        let mut plugin = Subhoofer::default();
+        // About 27 Hz, an octave under the note
+        set_normalized(&plugin, "Sub Min Input Freq", 0.3);
+        let max_period: u32 = (SAMPLE_RATE / SUB_RANGE_MIN_HZ) as u32;
+
+        // Note, rest long enough for the gate to close, the same note again
+        let note: usize = (BURST_SECONDS * SAMPLE_RATE) as usize;
+        let rest: usize = (0.5 * SAMPLE_RATE) as usize;
+        let total: usize = note + rest + note;
+        let mut left: Vec<f32> = (0..total)
+            .map(|idx| {
+                if idx >= note && idx < note + rest {
+                    return 0.0;
+                }
+                0.5 * (2.0 * PI * 55.0 * idx as f32 / SAMPLE_RATE).sin()
+            })
+            .collect();
+        let mut right: Vec<f32> = left.clone();
+
+        for block_start in (0..total).step_by(BLOCK_SIZE) {
+            let block_end: usize = (block_start + BLOCK_SIZE).min(total);
+            plugin.process_block(&mut [&mut left[block_start..block_end], &mut right[block_start..block_end]], SAMPLE_RATE);
+            assert!(plugin.input_period <= max_period, "measured a period of {} samples at {block_start}", plugin.input_period);
+            if block_start >= note + rest {
+                assert!(plugin.sub_range_gain > 0.99, "sub faded to {} at {block_start}", plugin.sub_range_gain);
+            }
+        }
+    }
+
+    // Sum of the sub over the first cycle of a note after a rest, with `cycles` of the note before it
+    fn first_cycle_after_rest(cycles: usize) -> f32 {
+        let _ftz = ScopedFtz::enable();
+        let mut plugin = Subhoofer::default();
+        plugin.sub_gate.set(-60.0, 6.0, 50.0, 1.0, SAMPLE_RATE);
+        let settings = SubSettings {
+            hoof_hardness: 0.0093,
+            sub_gain: 1.0,
+            sub_drive: 0.0,
+            divider_gains: [1.0, 0.0, 0.0],
+            sub_shape: SubShaper::ORIGINAL_SHAPE,
+            sub_phase: 0.0,
+            sub_invert: false,
+            auto_align: false,
+            min_freq: SUB_RANGE_MIN_HZ,
+            max_freq: SUB_RANGE_MAX_HZ,
+            overall_scale: 1.0,
+        };
+
+        let period: usize = (SAMPLE_RATE / 55.0) as usize;
+        // Both channels of a -6 dB note summed, like the sub source
+        let note = |idx: usize| (2.0 * PI * idx as f32 / period as f32).sin();
+        for idx in 0..cycles * period {
+            plugin.process_sub(note(idx), note(idx), &settings);
+        }
+        for _ in 0..SAMPLE_RATE as usize {
+            plugin.process_sub(0.0, 0.0, &settings);
+        }
+        (0..period).map(|idx| plugin.process_sub(note(idx), note(idx), &settings)).sum()
+    }
+
+    // Whichever way the dividers were left by the last note, the next one starts the sub the same way up
+    #[test]
+    fn sub_starts_on_the_same_polarity_after_a_rest() {
+        for cycles in [10, 11] {
+            let sum: f32 = first_cycle_after_rest(cycles);
+            assert!(sum > 0.0, "the sub started upside down after {cycles} cycles and a rest, sum {sum:e}");
+        }
+    }
+
+    // The gate sessions from before it get never closes, so the airwindows leak is all that stops
+    // DC sitting in the sub bump
+    #[test]
+    fn legacy_gate_never_leaks_dc() {
+        let _ftz = ScopedFtz::enable();
+        let mut plugin = Subhoofer::default();
+        // What StateMigration gives sessions from before the gate
+        set_normalized(&plugin, "Gate Threshold", 0.0);
+        plugin.osc_gate = 0.0;
+        plugin.iir_sub_bump_a = 0.001;
+        plugin.iir_sub_bump_b = -0.001;
+        plugin.iir_sub_bump_c = 0.001;
+
+        let mut left: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+        let mut right: [f32; BLOCK_SIZE] = [0.0; BLOCK_SIZE];
+        for _ in 0..(0.1 * SAMPLE_RATE) as usize / BLOCK_SIZE {
+            plugin.process_block(&mut [&mut left[..], &mut right[..]], SAMPLE_RATE);
+        }
+        assert_eq!([plugin.iir_sub_bump_a, plugin.iir_sub_bump_b, plugin.iir_sub_bump_c], [0.0; 3]);
+    }
+}